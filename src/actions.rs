@@ -0,0 +1,216 @@
+use near_sdk::{env, AccountId, Allowance, Gas, NearToken, Promise, PromiseOrValue, PromiseResult};
+use near_sdk::json_types::U128;
+use crate::state::FtWrapperContractState;
+use crate::types::{Action, ActionBatch, ActionOutcome, ActionStatus, BridgeTransferArgs, FtTransferArgs, RegistrationStatus};
+use crate::errors::FtWrapperError;
+use crate::events::FtWrapperEvent;
+use crate::ft::CALLBACK_GAS;
+use crate::ext_self;
+
+/// Fee forwarded to the MPC signer with each `ChainSignatureRequest` action.
+const SIGN_DEPOSIT: NearToken = NearToken::from_yoctonear(1);
+
+/// How long an action may run before its batch can be cancelled.
+const ACTION_BATCH_TIMEOUT_MS: u64 = 60 * 60 * 1000;
+
+/// Actions that act on the wrapper's own account or balance.
+fn is_manager_only(action: &Action) -> bool {
+    matches!(action, Action::FunctionCall { .. } | Action::Transfer { .. } | Action::AddKey { .. })
+}
+
+/// Actions that move tokens out of the sender's ledger balance.
+fn debits_ledger(action: &Action) -> bool {
+    matches!(action, Action::FtTransfer { .. } | Action::BridgeTransfer { .. })
+}
+
+/// Gas attached to the promises of `action` and to the `handle_action_result` step after it.
+fn action_gas(state: &FtWrapperContractState, action: &Action) -> u64 {
+    let call = state.cross_contract_gas + CALLBACK_GAS.as_gas();
+    let gas = match action {
+        Action::FtTransfer { token, receiver_id, .. } => {
+            let registered = matches!(
+                crate::ft::registration_status(state, token, receiver_id),
+                Some(RegistrationStatus::Registered | RegistrationStatus::Pending)
+            );
+            if registered { call } else { 2 * call }
+        }
+        Action::BridgeTransfer { .. } => state.cross_contract_gas + crate::ft::relay_callback_gas(state).as_gas(),
+        Action::ChainSignatureRequest { .. } => call,
+        Action::FunctionCall { gas, .. } => gas.as_gas(),
+        Action::Transfer { .. } | Action::AddKey { .. } => 0,
+    };
+    gas + CALLBACK_GAS.as_gas()
+}
+
+/// Runs `actions` in order as a promise chain: each action starts only after the previous one
+/// succeeded. The attached deposit pays the signer fee of chain signature requests; whatever
+/// is not used is refunded once the batch ends.
+pub fn execute_actions(state: &mut FtWrapperContractState, actions: Vec<Action>) -> Result<PromiseOrValue<Vec<ActionOutcome>>, FtWrapperError> {
+    // Like ft_transfer and bridge_transfer, spending the ledger needs a full access key
+    if actions.iter().any(debits_ledger) && env::attached_deposit().is_zero() {
+        return Err(FtWrapperError::InvalidDeposit);
+    }
+    run_actions(state, env::predecessor_account_id(), actions, env::attached_deposit())
+}
+
+/// Runs `actions` on behalf of `sender`, who has already been authenticated by the caller.
+pub fn run_actions(
+    state: &mut FtWrapperContractState,
+    sender: AccountId,
+    actions: Vec<Action>,
+    deposit: NearToken,
+) -> Result<PromiseOrValue<Vec<ActionOutcome>>, FtWrapperError> {
+    state.assert_balance()?;
+    if actions.iter().any(is_manager_only) && !state.is_manager(&sender) {
+        return Err(FtWrapperError::Unauthorized);
+    }
+    // Every step is paid for out of this call's gas, so a batch that cannot finish is refused
+    let required: u64 = actions.iter().map(|action| action_gas(state, action)).sum();
+    if Gas::from_gas(required) > env::prepaid_gas().saturating_sub(env::used_gas()) {
+        return Err(FtWrapperError::InsufficientGas);
+    }
+    let sign_requests = actions.iter()
+        .filter(|action| matches!(action, Action::ChainSignatureRequest { .. }))
+        .count() as u128;
+    let deposit = deposit.as_yoctonear();
+    if deposit < sign_requests * SIGN_DEPOSIT.as_yoctonear() {
+        return Err(FtWrapperError::InvalidDeposit);
+    }
+
+    let batch_id = state.next_action_batch_id;
+    state.next_action_batch_id += 1;
+    state.action_batches.insert(batch_id, ActionBatch {
+        id: batch_id,
+        sender,
+        actions,
+        outcomes: Vec::new(),
+        remaining_deposit: U128(deposit),
+        updated_at: env::block_timestamp_ms(),
+    });
+    Ok(run_next_action(state, batch_id))
+}
+
+fn dispatch(state: &mut FtWrapperContractState, sender: AccountId, action: Action) -> Result<Promise, FtWrapperError> {
+    match action {
+        Action::ChainSignatureRequest { target_chain, derivation_path, payload } => {
+            crate::signer::request_signature(state, sender, target_chain, derivation_path, payload, None, SIGN_DEPOSIT)
+        }
+        Action::FunctionCall { method_name, args, gas, deposit } => {
+            Ok(Promise::new(env::current_account_id()).function_call(method_name, args, deposit, gas))
+        }
+        Action::Transfer { deposit } => Ok(Promise::new(sender).transfer(deposit)),
+        Action::AddKey { public_key, allowance, receiver_id, method_names } => {
+            let allowance = allowance.and_then(Allowance::limited).unwrap_or(Allowance::Unlimited);
+            Ok(Promise::new(env::current_account_id())
+                .add_access_key_allowance(public_key, allowance, receiver_id, method_names.join(",")))
+        }
+        Action::FtTransfer { token, receiver_id, amount, memo } => {
+            crate::ft::ft_transfer(state, sender, FtTransferArgs { token, receiver_id, amount, memo })
+        }
+        Action::BridgeTransfer { token, amount, destination_chain, recipient } => {
+            crate::ft::bridge_from_balance(state, sender, BridgeTransferArgs { token, amount, destination_chain, recipient })
+        }
+    }
+}
+
+/// Starts the first action without an outcome, or ends the batch when none is left.
+fn run_next_action(state: &mut FtWrapperContractState, batch_id: u64) -> PromiseOrValue<Vec<ActionOutcome>> {
+    let batch = state.action_batches.get(&batch_id).expect("Action batch not found");
+    let index = batch.outcomes.len();
+    let Some(action) = batch.actions.get(index).cloned() else {
+        return PromiseOrValue::Value(finish_batch(state, batch_id));
+    };
+    let is_sign_request = matches!(action, Action::ChainSignatureRequest { .. });
+    let sender = batch.sender.clone();
+
+    match dispatch(state, sender, action) {
+        Ok(promise) => {
+            let batch = state.action_batches.get_mut(&batch_id).expect("Action batch not found");
+            batch.updated_at = env::block_timestamp_ms();
+            if is_sign_request {
+                batch.remaining_deposit.0 -= SIGN_DEPOSIT.as_yoctonear();
+            }
+            PromiseOrValue::Promise(promise.then(
+                ext_self::ext(env::current_account_id())
+                    .with_static_gas(CALLBACK_GAS)
+                    .with_unused_gas_weight(1)
+                    .handle_action_result(batch_id, index as u32),
+            ))
+        }
+        Err(error) => {
+            record_outcome(state, batch_id, ActionStatus::Failed, Some(error.to_string()));
+            PromiseOrValue::Value(finish_batch(state, batch_id))
+        }
+    }
+}
+
+fn record_outcome(state: &mut FtWrapperContractState, batch_id: u64, status: ActionStatus, error: Option<String>) {
+    let batch = state.action_batches.get_mut(&batch_id).expect("Action batch not found");
+    let index = batch.outcomes.len() as u32;
+    batch.outcomes.push(ActionOutcome { index, status, error });
+}
+
+/// Marks the actions that never ran as skipped, refunds the unused deposit and drops the batch.
+fn finish_batch(state: &mut FtWrapperContractState, batch_id: u64) -> Vec<ActionOutcome> {
+    let mut batch = state.action_batches.remove(&batch_id).expect("Action batch not found");
+    for index in batch.outcomes.len()..batch.actions.len() {
+        batch.outcomes.push(ActionOutcome { index: index as u32, status: ActionStatus::Skipped, error: None });
+    }
+    if batch.remaining_deposit.0 > 0 {
+        Promise::new(batch.sender.clone()).transfer(NearToken::from_yoctonear(batch.remaining_deposit.0));
+    }
+    FtWrapperEvent::ActionsExecuted {
+        batch_id,
+        sender: batch.sender,
+        outcomes: batch.outcomes.clone(),
+    }.emit();
+    batch.outcomes
+}
+
+/// Callback after action `index` of a batch: records its outcome and moves on to the next one.
+pub fn handle_action_result(state: &mut FtWrapperContractState, batch_id: u64, index: u32) -> PromiseOrValue<Vec<ActionOutcome>> {
+    // The sender may have cancelled the batch while this action was running
+    let Some(batch) = state.action_batches.get(&batch_id) else {
+        return PromiseOrValue::Value(Vec::new());
+    };
+    if batch.outcomes.len() != index as usize {
+        env::panic_str("Unexpected action result");
+    }
+    let action = &batch.actions[index as usize];
+    let is_sign_request = matches!(action, Action::ChainSignatureRequest { .. });
+    // Transfer callbacks credit the sender back on failure and report it by returning false;
+    // panicking instead would undo the credit
+    let is_transfer = debits_ledger(action);
+    let error = match env::promise_result(0) {
+        // The signature callback returns null when the signer did not produce a signature
+        PromiseResult::Successful(value) if is_sign_request && value == b"null" => Some("Signature request failed"),
+        PromiseResult::Successful(value) if is_transfer && value != b"true" => Some("Transfer failed"),
+        PromiseResult::Successful(_) => None,
+        _ => Some("Action failed"),
+    };
+    match error {
+        None => {
+            record_outcome(state, batch_id, ActionStatus::Succeeded, None);
+            run_next_action(state, batch_id)
+        }
+        Some(error) => {
+            record_outcome(state, batch_id, ActionStatus::Failed, Some(error.to_string()));
+            PromiseOrValue::Value(finish_batch(state, batch_id))
+        }
+    }
+}
+
+/// Ends a batch whose current action has not reported back within `ACTION_BATCH_TIMEOUT_MS`,
+/// for example because its callback ran out of gas. The action is recorded as failed and the
+/// unused deposit is refunded to the sender.
+pub fn cancel_action_batch(state: &mut FtWrapperContractState, batch_id: u64) -> Result<Vec<ActionOutcome>, FtWrapperError> {
+    let batch = state.action_batches.get(&batch_id).ok_or(FtWrapperError::ActionBatchNotFound)?;
+    if batch.sender != env::predecessor_account_id() {
+        return Err(FtWrapperError::Unauthorized);
+    }
+    if env::block_timestamp_ms() < batch.updated_at + ACTION_BATCH_TIMEOUT_MS {
+        return Err(FtWrapperError::ActionBatchTimeoutNotReached);
+    }
+    record_outcome(state, batch_id, ActionStatus::Failed, Some("Action timed out".to_string()));
+    Ok(finish_batch(state, batch_id))
+}
@@ -0,0 +1,104 @@
+use near_sdk::{env, bs58};
+use bech32::{hrp, segwit, Hrp};
+use crate::types::AddressFormat;
+use crate::errors::FtWrapperError;
+
+fn invalid(reason: &str) -> FtWrapperError {
+    FtWrapperError::InvalidRecipient(reason.to_string())
+}
+
+/// Checks that `address` is a well-formed address of the given chain family.
+pub fn validate_address(format: AddressFormat, address: &str) -> Result<(), FtWrapperError> {
+    match format {
+        AddressFormat::Evm => validate_evm_address(address),
+        AddressFormat::Bitcoin => validate_bitcoin_address(address, hrp::BC, &[0x00, 0x05]),
+        AddressFormat::BitcoinTestnet => validate_bitcoin_address(address, hrp::TB, &[0x6f, 0xc4]),
+        AddressFormat::Solana => validate_solana_address(address),
+    }
+}
+
+/// `0x`-prefixed 20-byte hex. Mixed-case addresses must carry a valid EIP-55 checksum;
+/// all-lowercase or all-uppercase addresses carry no checksum and are accepted as is.
+fn validate_evm_address(address: &str) -> Result<(), FtWrapperError> {
+    let hex = address.strip_prefix("0x").ok_or_else(|| invalid("EVM address must start with 0x"))?;
+    if hex.len() != 40 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid("EVM address must be 40 hex characters"));
+    }
+    let has_lower = hex.chars().any(|c| c.is_ascii_lowercase());
+    let has_upper = hex.chars().any(|c| c.is_ascii_uppercase());
+    if has_lower && has_upper && to_eip55_hex(hex) != hex {
+        return Err(invalid("EVM address has an invalid EIP-55 checksum"));
+    }
+    Ok(())
+}
+
+/// Applies the EIP-55 mixed-case checksum to 40 hex characters (without `0x`).
+pub fn to_eip55_hex(hex: &str) -> String {
+    let lower = hex.to_ascii_lowercase();
+    let hash = env::keccak256_array(lower.as_bytes());
+    lower.chars().enumerate().map(|(i, c)| {
+        let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+        if c.is_ascii_alphabetic() && nibble >= 8 { c.to_ascii_uppercase() } else { c }
+    }).collect()
+}
+
+/// Accepts segwit addresses (bech32 for v0, bech32m for v1+) with the network's `hrp`, and
+/// base58check P2PKH/P2SH addresses with one of the network's `versions`.
+fn validate_bitcoin_address(address: &str, hrp: Hrp, versions: &[u8]) -> Result<(), FtWrapperError> {
+    let segwit_prefix = format!("{}1", hrp.as_str());
+    if address.to_ascii_lowercase().starts_with(&segwit_prefix) {
+        let (decoded_hrp, _, _) = segwit::decode(address)
+            .map_err(|_| invalid("Bitcoin segwit address failed bech32 validation"))?;
+        if decoded_hrp != hrp {
+            return Err(invalid("Bitcoin segwit address is for another network"));
+        }
+        return Ok(());
+    }
+
+    let decoded = bs58::decode(address).into_vec()
+        .map_err(|_| invalid("Bitcoin address is neither bech32 nor base58"))?;
+    if decoded.len() != 25 {
+        return Err(invalid("Bitcoin base58 address must decode to 25 bytes"));
+    }
+    let (payload, checksum) = decoded.split_at(21);
+    if env::sha256_array(&env::sha256_array(payload))[..4] != *checksum {
+        return Err(invalid("Bitcoin base58 address has an invalid checksum"));
+    }
+    if !versions.contains(&payload[0]) {
+        return Err(invalid("Bitcoin base58 address has an unsupported version for this network"));
+    }
+    Ok(())
+}
+
+/// Base58-encoded 32-byte public key (or program-derived address).
+fn validate_solana_address(address: &str) -> Result<(), FtWrapperError> {
+    let decoded = bs58::decode(address).into_vec()
+        .map_err(|_| invalid("Solana address must be base58"))?;
+    if decoded.len() != 32 {
+        return Err(invalid("Solana address must decode to 32 bytes"));
+    }
+    Ok(())
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()).collect()
+}
+
+/// EIP-55 address of an uncompressed secp256k1 public key given as `x ‖ y`.
+pub fn evm_address(public_key: &[u8; 64]) -> String {
+    let hash = env::keccak256_array(public_key);
+    format!("0x{}", to_eip55_hex(&to_hex(&hash[12..])))
+}
+
+/// P2WPKH (bech32, witness v0) address of a compressed secp256k1 public key.
+pub fn bitcoin_p2wpkh_address(public_key: &[u8; 33], hrp: Hrp) -> String {
+    let program = env::ripemd160_array(&env::sha256_array(public_key));
+    segwit::encode_v0(hrp, &program).expect("20-byte witness program is always encodable")
+}
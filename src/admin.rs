@@ -1,5 +1,6 @@
-use near_sdk::{env, AccountId};
+use near_sdk::{env, AccountId, CurveType, PublicKey};
 use crate::state::FtWrapperContractState;
+use crate::types::StorageSponsorship;
 use crate::errors::FtWrapperError;
 use crate::events::FtWrapperEvent;
 use near_sdk::json_types::U128;
@@ -52,4 +53,107 @@ pub fn set_storage_deposit(state: &mut FtWrapperContractState, storage_deposit:
     state.storage_deposit = storage_deposit;
     FtWrapperEvent::StorageDepositUpdated { storage_deposit }.emit();
     Ok(())
-}
\ No newline at end of file
+}
+
+pub fn set_storage_sponsorship(state: &mut FtWrapperContractState, policy: StorageSponsorship) -> Result<(), FtWrapperError> {
+    let caller = env::predecessor_account_id();
+    if !state.is_manager(&caller) {
+        return Err(FtWrapperError::Unauthorized);
+    }
+    state.storage_sponsorship = policy;
+    FtWrapperEvent::StorageSponsorshipUpdated { policy }.emit();
+    Ok(())
+}
+
+pub fn set_bridge_refund_timeout(state: &mut FtWrapperContractState, timeout_ms: u64) -> Result<(), FtWrapperError> {
+    let caller = env::predecessor_account_id();
+    if !state.is_manager(&caller) {
+        return Err(FtWrapperError::Unauthorized);
+    }
+    state.bridge_refund_timeout_ms = timeout_ms;
+    FtWrapperEvent::BridgeRefundTimeoutUpdated { timeout_ms }.emit();
+    Ok(())
+}
+
+pub fn add_mpc_public_key(state: &mut FtWrapperContractState, public_key: PublicKey) -> Result<(), FtWrapperError> {
+    let caller = env::predecessor_account_id();
+    if !state.is_manager(&caller) {
+        return Err(FtWrapperError::Unauthorized);
+    }
+    if state.mpc_public_keys.contains(&public_key) {
+        return Err(FtWrapperError::MpcKeyAlreadyExists);
+    }
+    state.mpc_public_keys.push(public_key.clone());
+    FtWrapperEvent::MpcKeyAdded { public_key }.emit();
+    Ok(())
+}
+
+pub fn rotate_mpc_public_key(state: &mut FtWrapperContractState, old_public_key: PublicKey, new_public_key: PublicKey) -> Result<(), FtWrapperError> {
+    let caller = env::predecessor_account_id();
+    if !state.is_manager(&caller) {
+        return Err(FtWrapperError::Unauthorized);
+    }
+    if state.mpc_public_keys.contains(&new_public_key) {
+        return Err(FtWrapperError::MpcKeyAlreadyExists);
+    }
+    let index = state.mpc_public_keys.iter().position(|k| k == &old_public_key)
+        .ok_or(FtWrapperError::MpcKeyNotFound)?;
+    state.mpc_public_keys[index] = new_public_key.clone();
+    FtWrapperEvent::MpcKeyRotated { old_public_key, new_public_key }.emit();
+    Ok(())
+}
+
+pub fn retire_mpc_public_key(state: &mut FtWrapperContractState, public_key: PublicKey) -> Result<(), FtWrapperError> {
+    let caller = env::predecessor_account_id();
+    if !state.is_manager(&caller) {
+        return Err(FtWrapperError::Unauthorized);
+    }
+    if let Some(index) = state.mpc_public_keys.iter().position(|k| k == &public_key) {
+        state.mpc_public_keys.remove(index);
+        FtWrapperEvent::MpcKeyRetired { public_key }.emit();
+        Ok(())
+    } else {
+        Err(FtWrapperError::MpcKeyNotFound)
+    }
+}
+
+pub fn set_mpc_signer(state: &mut FtWrapperContractState, signer_contract: AccountId, key_version: u32) -> Result<(), FtWrapperError> {
+    let caller = env::predecessor_account_id();
+    if !state.is_manager(&caller) {
+        return Err(FtWrapperError::Unauthorized);
+    }
+    state.mpc_signer_contract = signer_contract.clone();
+    state.mpc_key_version = key_version;
+    FtWrapperEvent::MpcSignerUpdated { signer_contract, key_version }.emit();
+    Ok(())
+}
+
+pub fn set_signature_request_timeout(state: &mut FtWrapperContractState, timeout_ms: u64) -> Result<(), FtWrapperError> {
+    let caller = env::predecessor_account_id();
+    if !state.is_manager(&caller) {
+        return Err(FtWrapperError::Unauthorized);
+    }
+    state.signature_request_timeout_ms = timeout_ms;
+    FtWrapperEvent::SignatureRequestTimeoutUpdated { timeout_ms }.emit();
+    Ok(())
+}
+
+/// Stores the signer's root key for its curve; derived addresses are computed from it.
+pub fn set_mpc_root_public_key(state: &mut FtWrapperContractState, public_key: PublicKey) -> Result<(), FtWrapperError> {
+    let caller = env::predecessor_account_id();
+    if !state.is_manager(&caller) {
+        return Err(FtWrapperError::Unauthorized);
+    }
+    match public_key.curve_type() {
+        CurveType::SECP256K1 => {
+            crate::signer::secp256k1_point(&public_key)?;
+            state.mpc_root_secp256k1_key = Some(public_key.clone());
+        }
+        CurveType::ED25519 => {
+            crate::signer::ed25519_point(&public_key)?;
+            state.mpc_root_ed25519_key = Some(public_key.clone());
+        }
+    }
+    FtWrapperEvent::MpcRootKeyUpdated { public_key }.emit();
+    Ok(())
+}
@@ -0,0 +1,163 @@
+use near_sdk::{bs58, env};
+use bech32::{hrp, segwit, Hrp};
+use crate::types::{AddressFormat, BitcoinScriptType, BitcoinTransaction, SignatureResponse};
+use crate::address::from_hex;
+use crate::errors::FtWrapperError;
+
+const DEFAULT_SEQUENCE: u32 = 0xffff_ffff;
+const SIGHASH_ALL: u32 = 1;
+
+fn invalid(reason: &str) -> FtWrapperError {
+    FtWrapperError::InvalidBitcoinTransaction(reason.to_string())
+}
+
+fn double_sha256(data: &[u8]) -> [u8; 32] {
+    env::sha256_array(&env::sha256_array(data))
+}
+
+fn hash160(data: &[u8]) -> [u8; 20] {
+    env::ripemd160_array(&env::sha256_array(data))
+}
+
+fn push_varint(out: &mut Vec<u8>, n: usize) {
+    match n {
+        0..=0xfc => out.push(n as u8),
+        0xfd..=0xffff => {
+            out.push(0xfd);
+            out.extend_from_slice(&(n as u16).to_le_bytes());
+        }
+        _ => {
+            out.push(0xfe);
+            out.extend_from_slice(&(n as u32).to_le_bytes());
+        }
+    }
+}
+
+fn push_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    push_varint(out, bytes.len());
+    out.extend_from_slice(bytes);
+}
+
+/// Transaction ids are displayed byte-reversed; outpoints use internal order.
+fn parse_txid(txid: &str) -> Result<[u8; 32], FtWrapperError> {
+    let mut bytes: [u8; 32] = from_hex(txid)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| invalid("txid must be 64 hex characters"))?;
+    bytes.reverse();
+    Ok(bytes)
+}
+
+/// scriptPubKey paying to `address` on the network of `format`.
+fn script_pubkey(format: AddressFormat, address: &str) -> Result<Vec<u8>, FtWrapperError> {
+    crate::address::validate_address(format, address)?;
+    let network_hrp: Hrp = if format == AddressFormat::Bitcoin { hrp::BC } else { hrp::TB };
+    if address.to_ascii_lowercase().starts_with(&format!("{}1", network_hrp.as_str())) {
+        let (_, version, program) = segwit::decode(address)
+            .map_err(|_| invalid("Output address failed bech32 validation"))?;
+        let version = version.to_u8();
+        let mut script = vec![if version == 0 { 0x00 } else { 0x50 + version }];
+        push_bytes(&mut script, &program);
+        return Ok(script);
+    }
+    // Base58check: version byte, 20-byte hash, checksum (validated above)
+    let decoded = bs58::decode(address).into_vec().map_err(|_| invalid("Output address is not base58"))?;
+    let hash = &decoded[1..21];
+    Ok(match decoded[0] {
+        0x00 | 0x6f => [&[0x76, 0xa9, 0x14][..], hash, &[0x88, 0xac]].concat(),
+        _ => [&[0xa9, 0x14][..], hash, &[0x87]].concat(),
+    })
+}
+
+/// Legacy serialization (no witnesses) of `tx`; its double SHA-256 is the txid.
+pub fn serialize_unsigned(format: AddressFormat, tx: &BitcoinTransaction) -> Result<Vec<u8>, FtWrapperError> {
+    if tx.inputs.is_empty() || tx.outputs.is_empty() {
+        return Err(invalid("Transaction needs at least one input and one output"));
+    }
+    let mut out = tx.version.to_le_bytes().to_vec();
+    push_varint(&mut out, tx.inputs.len());
+    for input in &tx.inputs {
+        out.extend_from_slice(&parse_txid(&input.txid)?);
+        out.extend_from_slice(&input.vout.to_le_bytes());
+        push_varint(&mut out, 0); // empty scriptSig: segwit inputs sign in the witness
+        out.extend_from_slice(&input.sequence.unwrap_or(DEFAULT_SEQUENCE).to_le_bytes());
+    }
+    push_varint(&mut out, tx.outputs.len());
+    for output in &tx.outputs {
+        out.extend_from_slice(&output.value.0.to_le_bytes());
+        push_bytes(&mut out, &script_pubkey(format, &output.address)?);
+    }
+    out.extend_from_slice(&tx.lock_time.to_le_bytes());
+    Ok(out)
+}
+
+/// BIP-143 SIGHASH_ALL digest of every input, all spent by the key with `public_key`.
+pub fn segwit_v0_sighashes(tx: &BitcoinTransaction, format: AddressFormat, public_key: &[u8]) -> Result<Vec<[u8; 32]>, FtWrapperError> {
+    let mut prevouts = Vec::new();
+    let mut sequences = Vec::new();
+    for input in &tx.inputs {
+        prevouts.extend_from_slice(&parse_txid(&input.txid)?);
+        prevouts.extend_from_slice(&input.vout.to_le_bytes());
+        sequences.extend_from_slice(&input.sequence.unwrap_or(DEFAULT_SEQUENCE).to_le_bytes());
+    }
+    let mut outputs = Vec::new();
+    for output in &tx.outputs {
+        outputs.extend_from_slice(&output.value.0.to_le_bytes());
+        push_bytes(&mut outputs, &script_pubkey(format, &output.address)?);
+    }
+    let hash_prevouts = double_sha256(&prevouts);
+    let hash_sequence = double_sha256(&sequences);
+    let hash_outputs = double_sha256(&outputs);
+
+    tx.inputs.iter().map(|input| {
+        // P2WPKH spends are signed with the equivalent P2PKH script as scriptCode
+        let script_code = match input.script_type {
+            BitcoinScriptType::P2wpkh => [&[0x76, 0xa9, 0x14][..], &hash160(public_key), &[0x88, 0xac]].concat(),
+        };
+        let mut preimage = tx.version.to_le_bytes().to_vec();
+        preimage.extend_from_slice(&hash_prevouts);
+        preimage.extend_from_slice(&hash_sequence);
+        preimage.extend_from_slice(&parse_txid(&input.txid)?);
+        preimage.extend_from_slice(&input.vout.to_le_bytes());
+        push_bytes(&mut preimage, &script_code);
+        preimage.extend_from_slice(&input.value.0.to_le_bytes());
+        preimage.extend_from_slice(&input.sequence.unwrap_or(DEFAULT_SEQUENCE).to_le_bytes());
+        preimage.extend_from_slice(&hash_outputs);
+        preimage.extend_from_slice(&tx.lock_time.to_le_bytes());
+        preimage.extend_from_slice(&SIGHASH_ALL.to_le_bytes());
+        Ok(double_sha256(&preimage))
+    }).collect()
+}
+
+/// DER-encoded, low-s signature with the SIGHASH_ALL byte appended, as pushed in a witness.
+fn witness_signature(signature: &SignatureResponse) -> Option<Vec<u8>> {
+    let big_r = from_hex(&signature.big_r.affine_point)?;
+    let s = from_hex(&signature.s.scalar)?;
+    if big_r.len() != 33 || s.len() != 32 {
+        return None;
+    }
+    let parsed = k256::ecdsa::Signature::from_scalars(
+        *k256::FieldBytes::from_slice(&big_r[1..]),
+        *k256::FieldBytes::from_slice(&s),
+    ).ok()?;
+    let normalized = parsed.normalize_s().unwrap_or(parsed);
+    let mut der = normalized.to_der().as_bytes().to_vec();
+    der.push(SIGHASH_ALL as u8);
+    Some(der)
+}
+
+/// Inserts the segwit marker and one `[signature, public_key]` witness per input into the
+/// legacy serialization.
+pub fn assemble_signed(unsigned: &[u8], public_key: &[u8], signatures: &[SignatureResponse]) -> Option<Vec<u8>> {
+    let (version, rest) = unsigned.split_at(4);
+    let (body, lock_time) = rest.split_at(rest.len() - 4);
+    let mut out = version.to_vec();
+    out.extend_from_slice(&[0x00, 0x01]);
+    out.extend_from_slice(body);
+    for signature in signatures {
+        push_varint(&mut out, 2);
+        push_bytes(&mut out, &witness_signature(signature)?);
+        push_bytes(&mut out, public_key);
+    }
+    out.extend_from_slice(lock_time);
+    Some(out)
+}
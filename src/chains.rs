@@ -0,0 +1,96 @@
+use near_sdk::env;
+use near_sdk::json_types::U128;
+use crate::state::FtWrapperContractState;
+use crate::types::ChainConfig;
+use crate::errors::FtWrapperError;
+use crate::events::FtWrapperEvent;
+
+fn validate_config(config: &ChainConfig) -> Result<(), FtWrapperError> {
+    if config.chain_id.is_empty() || config.min_amount.0 > config.max_amount.0 {
+        return Err(FtWrapperError::InvalidChainConfig);
+    }
+    Ok(())
+}
+
+pub fn add_chain(state: &mut FtWrapperContractState, config: ChainConfig) -> Result<(), FtWrapperError> {
+    let caller = env::predecessor_account_id();
+    if !state.is_manager(&caller) {
+        return Err(FtWrapperError::Unauthorized);
+    }
+    validate_config(&config)?;
+    if state.chains.contains_key(&config.chain_id) {
+        return Err(FtWrapperError::ChainAlreadyExists);
+    }
+    let chain_id = config.chain_id.clone();
+    state.chains.insert(chain_id.clone(), config);
+    FtWrapperEvent::ChainAdded { chain_id }.emit();
+    Ok(())
+}
+
+pub fn update_chain(state: &mut FtWrapperContractState, config: ChainConfig) -> Result<(), FtWrapperError> {
+    let caller = env::predecessor_account_id();
+    if !state.is_manager(&caller) {
+        return Err(FtWrapperError::Unauthorized);
+    }
+    validate_config(&config)?;
+    if !state.chains.contains_key(&config.chain_id) {
+        return Err(FtWrapperError::UnknownChain);
+    }
+    let chain_id = config.chain_id.clone();
+    state.chains.insert(chain_id.clone(), config);
+    FtWrapperEvent::ChainUpdated { chain_id }.emit();
+    Ok(())
+}
+
+pub fn set_chain_enabled(state: &mut FtWrapperContractState, chain_id: String, enabled: bool) -> Result<(), FtWrapperError> {
+    let caller = env::predecessor_account_id();
+    if !state.is_manager(&caller) {
+        return Err(FtWrapperError::Unauthorized);
+    }
+    let config = state.chains.get_mut(&chain_id).ok_or(FtWrapperError::UnknownChain)?;
+    config.enabled = enabled;
+    FtWrapperEvent::ChainUpdated { chain_id }.emit();
+    Ok(())
+}
+
+pub fn remove_chain(state: &mut FtWrapperContractState, chain_id: String) -> Result<(), FtWrapperError> {
+    let caller = env::predecessor_account_id();
+    if !state.is_manager(&caller) {
+        return Err(FtWrapperError::Unauthorized);
+    }
+    if state.chains.remove(&chain_id).is_none() {
+        return Err(FtWrapperError::UnknownChain);
+    }
+    FtWrapperEvent::ChainRemoved { chain_id }.emit();
+    Ok(())
+}
+
+/// Returns the configuration of a registered chain that is currently enabled.
+pub fn enabled_chain<'a>(state: &'a FtWrapperContractState, chain_id: &str) -> Result<&'a ChainConfig, FtWrapperError> {
+    let config = state.chains.get(chain_id).ok_or(FtWrapperError::UnknownChain)?;
+    if !config.enabled {
+        return Err(FtWrapperError::ChainDisabled);
+    }
+    Ok(config)
+}
+
+/// Checks that an outbound transfer of `amount` to `recipient` on `chain_id` is allowed.
+pub fn assert_outbound_transfer(state: &FtWrapperContractState, chain_id: &str, recipient: &str, amount: U128) -> Result<(), FtWrapperError> {
+    let config = enabled_chain(state, chain_id)?;
+    crate::address::validate_address(config.address_format, recipient)?;
+    if amount.0 < config.min_amount.0 {
+        return Err(FtWrapperError::AmountTooLow);
+    }
+    if amount.0 > config.max_amount.0 {
+        return Err(FtWrapperError::AmountTooHigh);
+    }
+    Ok(())
+}
+
+pub fn get_chain(state: &FtWrapperContractState, chain_id: String) -> Option<ChainConfig> {
+    state.chains.get(&chain_id).cloned()
+}
+
+pub fn get_chains(state: &FtWrapperContractState) -> Vec<ChainConfig> {
+    state.chains.values().cloned().collect()
+}
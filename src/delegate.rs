@@ -0,0 +1,139 @@
+use near_sdk::{env, borsh, AccountId, CurveType, NearToken, PromiseOrValue, PublicKey};
+use crate::state::FtWrapperContractState;
+use crate::types::{Action, ActionOutcome, DelegateAction, IntentKey, SignedDelegateAction};
+use crate::errors::FtWrapperError;
+use crate::events::FtWrapperEvent;
+
+/// NEP-461 discriminant prepended to a borsh-serialized delegate action before hashing.
+const DELEGATE_ACTION_PREFIX: u32 = (1 << 30) + 366;
+
+/// Actions an intent key can be scoped to, by the name used in `IntentKey::methods`.
+const INTENT_METHODS: [&str; 2] = ["ft_transfer", "bridge_transfer"];
+
+/// Intent keys one account can hold at a time. The wrapper pays for their storage.
+const MAX_INTENT_KEYS: usize = 10;
+
+fn action_method(action: &Action) -> Option<&'static str> {
+    match action {
+        Action::FtTransfer { .. } => Some("ft_transfer"),
+        Action::BridgeTransfer { .. } => Some("bridge_transfer"),
+        _ => None,
+    }
+}
+
+fn is_active(key: &IntentKey) -> bool {
+    key.expires_at.is_none_or(|expires_at| expires_at > env::block_timestamp_ms())
+}
+
+/// Registers `public_key` as a key the caller signs delegate actions with, optionally until
+/// `expires_at` and only for `methods`.
+pub fn register_intent_key(
+    state: &mut FtWrapperContractState,
+    public_key: PublicKey,
+    expires_at: Option<u64>,
+    methods: Vec<String>,
+) -> Result<(), FtWrapperError> {
+    if public_key.curve_type() != CurveType::ED25519
+        || expires_at.is_some_and(|expires_at| expires_at <= env::block_timestamp_ms())
+        || methods.iter().any(|method| !INTENT_METHODS.contains(&method.as_str()))
+    {
+        return Err(FtWrapperError::InvalidIntentKey);
+    }
+    let account_id = env::predecessor_account_id();
+    let mut keys = state.intent_keys.get(&account_id).cloned().unwrap_or_default();
+    if keys.iter().any(|key| key.public_key == public_key) {
+        return Err(FtWrapperError::IntentKeyAlreadyExists);
+    }
+    // Expired keys cannot sign anything, so they give up their place
+    keys.retain(is_active);
+    if keys.len() >= MAX_INTENT_KEYS {
+        return Err(FtWrapperError::TooManyIntentKeys);
+    }
+    keys.push(IntentKey { public_key: public_key.clone(), expires_at, methods: methods.clone() });
+    state.intent_keys.insert(account_id.clone(), keys);
+    FtWrapperEvent::IntentKeyRegistered { account_id, public_key, expires_at, methods }.emit();
+    Ok(())
+}
+
+/// Removes one of the caller's intent keys. Its nonces stay used.
+pub fn revoke_intent_key(state: &mut FtWrapperContractState, public_key: PublicKey) -> Result<(), FtWrapperError> {
+    let account_id = env::predecessor_account_id();
+    let mut keys = state.intent_keys.get(&account_id).cloned().unwrap_or_default();
+    let index = keys.iter()
+        .position(|key| key.public_key == public_key)
+        .ok_or(FtWrapperError::IntentKeyNotFound)?;
+    keys.remove(index);
+    if keys.is_empty() {
+        state.intent_keys.remove(&account_id);
+    } else {
+        state.intent_keys.insert(account_id.clone(), keys);
+    }
+    FtWrapperEvent::IntentKeyRevoked { account_id, public_key }.emit();
+    Ok(())
+}
+
+/// SHA-256 of the NEP-461 prefix followed by the borsh-serialized `delegate_action`.
+pub fn delegate_action_hash(delegate_action: &DelegateAction) -> [u8; 32] {
+    let mut message = borsh::to_vec(&DELEGATE_ACTION_PREFIX).expect("Failed to serialize prefix");
+    message.extend(borsh::to_vec(delegate_action).expect("Failed to serialize delegate action"));
+    env::sha256_array(&message)
+}
+
+/// Checks a delegate action signed by `sender_id` and runs its actions as that sender. The
+/// caller, usually a relayer, pays the gas; the sender's nonce is consumed even if an inner
+/// action later fails.
+pub fn submit_signed_delegate(
+    state: &mut FtWrapperContractState,
+    signed: SignedDelegateAction,
+) -> Result<PromiseOrValue<Vec<ActionOutcome>>, FtWrapperError> {
+    let delegate = signed.delegate_action;
+    if delegate.receiver_id != env::current_account_id() || delegate.actions.is_empty() {
+        return Err(FtWrapperError::InvalidDelegateAction);
+    }
+    let methods: Vec<&str> = delegate.actions.iter()
+        .map(action_method)
+        .collect::<Option<_>>()
+        .ok_or(FtWrapperError::ActionNotSupported)?;
+    if env::block_height() > delegate.max_block_height {
+        return Err(FtWrapperError::DelegateActionExpired);
+    }
+
+    let key = state.intent_keys.get(&delegate.sender_id)
+        .and_then(|keys| keys.iter().find(|key| key.public_key == delegate.public_key))
+        .ok_or(FtWrapperError::IntentKeyNotFound)?;
+    if !is_active(key) {
+        return Err(FtWrapperError::IntentKeyExpired);
+    }
+    if !key.methods.is_empty() && !methods.iter().all(|method| key.methods.iter().any(|allowed| allowed == method)) {
+        return Err(FtWrapperError::IntentKeyOutOfScope);
+    }
+    if delegate.nonce <= get_intent_nonce(state, delegate.sender_id.clone()) {
+        return Err(FtWrapperError::InvalidNonce);
+    }
+    let signature: &[u8; 64] = signed.signature.as_slice().try_into()
+        .map_err(|_| FtWrapperError::InvalidDelegateSignature)?;
+    let key_bytes: &[u8; 32] = key.public_key.as_bytes()[1..].try_into().unwrap();
+    if !env::ed25519_verify(signature, &delegate_action_hash(&delegate), key_bytes) {
+        return Err(FtWrapperError::InvalidDelegateSignature);
+    }
+    state.intent_nonces.insert(delegate.sender_id.clone(), delegate.nonce);
+
+    FtWrapperEvent::DelegateActionExecuted {
+        sender: delegate.sender_id.clone(),
+        relayer: env::predecessor_account_id(),
+        nonce: delegate.nonce,
+    }.emit();
+    crate::actions::run_actions(state, delegate.sender_id, delegate.actions, NearToken::from_yoctonear(0))
+}
+
+/// Keys of `account_id` that have not expired.
+pub fn get_intent_keys(state: &FtWrapperContractState, account_id: AccountId) -> Vec<IntentKey> {
+    state.intent_keys.get(&account_id)
+        .map(|keys| keys.iter().filter(|key| is_active(key)).cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Last nonce `account_id` used; the next delegate action must carry a greater one.
+pub fn get_intent_nonce(state: &FtWrapperContractState, account_id: AccountId) -> u64 {
+    state.intent_nonces.get(&account_id).copied().unwrap_or(0)
+}
@@ -1,6 +1,7 @@
 use near_sdk::borsh::{self, BorshSerialize, BorshDeserialize};
 use near_sdk_macros::NearSchema;
 use near_sdk::FunctionError;
+use std::fmt;
 
 #[derive(Debug, NearSchema, BorshSerialize, BorshDeserialize)]
 #[abi(borsh)]
@@ -13,11 +14,57 @@ pub enum FtWrapperError {
     NonZeroBalance,
     Unauthorized,
     LowBalance,
+    InvalidSignature,
+    MpcKeyAlreadyExists,
+    MpcKeyNotFound,
+    MessageAlreadyProcessed,
+    InvalidBridgeMessage,
+    BridgeMessageMismatch,
+    BridgeMessageExpired,
+    InvalidTransferMessage,
+    TransferNotFound,
+    InvalidTransferStatus,
+    RefundTimeoutNotReached,
+    TokenNotBridged,
+    BridgedTokenExists,
+    BridgedTokenCodeMissing,
+    InvalidAccountPrefix,
+    UnknownChain,
+    ChainDisabled,
+    ChainAlreadyExists,
+    InvalidChainConfig,
+    AmountTooHigh,
+    InvalidRecipient(String),
+    InvalidBitcoinTransaction(String),
+    InvalidSignaturePayload,
+    SignatureRequestNotFound,
+    InvalidSignatureRequestStatus,
+    MpcRootKeyMissing,
+    InvalidMpcRootKey,
+    UnsupportedTransaction,
+    ActionNotSupported,
+    InsufficientBalance,
+    InvalidReconciliationBatch,
+    RegistrationPending,
+    InvalidIntentKey,
+    IntentKeyAlreadyExists,
+    IntentKeyNotFound,
+    IntentKeyExpired,
+    IntentKeyOutOfScope,
+    InvalidDelegateAction,
+    InvalidDelegateSignature,
+    DelegateActionExpired,
+    InvalidNonce,
+    InsufficientGas,
+    InsufficientLockedBalance,
+    ActionBatchNotFound,
+    ActionBatchTimeoutNotReached,
+    TooManyIntentKeys,
 }
 
-impl FunctionError for FtWrapperError {
-    fn panic(&self) -> ! {
-        panic!("{}", match self {
+impl fmt::Display for FtWrapperError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
             FtWrapperError::TokenNotSupported => "Token not supported",
             FtWrapperError::AmountTooLow => "Amount too low",
             FtWrapperError::InvalidDeposit => "Invalid deposit amount",
@@ -26,6 +73,59 @@ impl FunctionError for FtWrapperError {
             FtWrapperError::NonZeroBalance => "Non-zero token balance",
             FtWrapperError::Unauthorized => "Unauthorized access",
             FtWrapperError::LowBalance => "Contract balance too low",
-        })
+            FtWrapperError::InvalidSignature => "Invalid MPC signature",
+            FtWrapperError::MpcKeyAlreadyExists => "MPC public key already registered",
+            FtWrapperError::MpcKeyNotFound => "MPC public key not found",
+            FtWrapperError::MessageAlreadyProcessed => "Bridge message already processed",
+            FtWrapperError::InvalidBridgeMessage => "Invalid bridge message",
+            FtWrapperError::BridgeMessageMismatch => "Transfer arguments do not match the signed bridge message",
+            FtWrapperError::BridgeMessageExpired => "Bridge message expired",
+            FtWrapperError::InvalidTransferMessage => "Invalid ft_on_transfer message",
+            FtWrapperError::TransferNotFound => "Bridge transfer not found",
+            FtWrapperError::InvalidTransferStatus => "Bridge transfer status does not allow this operation",
+            FtWrapperError::RefundTimeoutNotReached => "Bridge transfer refund timeout not reached",
+            FtWrapperError::TokenNotBridged => "Token is not a bridged token for this chain",
+            FtWrapperError::BridgedTokenExists => "Bridged token already deployed",
+            FtWrapperError::BridgedTokenCodeMissing => "Bridged token code not set",
+            FtWrapperError::InvalidAccountPrefix => "Invalid sub-account prefix",
+            FtWrapperError::UnknownChain => "Chain not registered",
+            FtWrapperError::ChainDisabled => "Chain disabled",
+            FtWrapperError::ChainAlreadyExists => "Chain already registered",
+            FtWrapperError::InvalidChainConfig => "Invalid chain configuration",
+            FtWrapperError::AmountTooHigh => "Amount too high",
+            FtWrapperError::InvalidSignaturePayload => "Signature payload must be 32 bytes",
+            FtWrapperError::SignatureRequestNotFound => "Signature request not found",
+            FtWrapperError::InvalidSignatureRequestStatus => "Signature request status does not allow this operation",
+            FtWrapperError::MpcRootKeyMissing => "MPC root public key not set for this curve",
+            FtWrapperError::InvalidMpcRootKey => "Invalid MPC root public key",
+            FtWrapperError::UnsupportedTransaction => "Transaction type not supported on the target chain",
+            FtWrapperError::ActionNotSupported => "Action not supported",
+            FtWrapperError::InsufficientBalance => "Insufficient balance",
+            FtWrapperError::RegistrationPending => "Storage deposit already in flight",
+            FtWrapperError::InvalidReconciliationBatch => "Reconciliation needs between 1 and 10 accounts",
+            FtWrapperError::InvalidIntentKey => "Invalid intent key",
+            FtWrapperError::IntentKeyAlreadyExists => "Intent key already registered",
+            FtWrapperError::IntentKeyNotFound => "Intent key not registered for the sender",
+            FtWrapperError::IntentKeyExpired => "Intent key expired",
+            FtWrapperError::IntentKeyOutOfScope => "Intent key not allowed to sign this action",
+            FtWrapperError::InvalidDelegateAction => "Invalid delegate action",
+            FtWrapperError::InvalidDelegateSignature => "Invalid delegate action signature",
+            FtWrapperError::DelegateActionExpired => "Delegate action expired",
+            FtWrapperError::InvalidNonce => "Nonce must be greater than the last used nonce",
+            FtWrapperError::InsufficientGas => "Attached gas does not cover every action",
+            FtWrapperError::InsufficientLockedBalance => "Release exceeds the tokens locked for the bridge",
+            FtWrapperError::ActionBatchNotFound => "Action batch not found",
+            FtWrapperError::ActionBatchTimeoutNotReached => "Action batch timeout not reached",
+            FtWrapperError::TooManyIntentKeys => "Account has too many intent keys",
+            FtWrapperError::InvalidRecipient(reason) => return write!(f, "Invalid recipient address: {}", reason),
+            FtWrapperError::InvalidBitcoinTransaction(reason) => return write!(f, "Invalid Bitcoin transaction: {}", reason),
+        };
+        f.write_str(message)
+    }
+}
+
+impl FunctionError for FtWrapperError {
+    fn panic(&self) -> ! {
+        panic!("{}", self)
     }
-}
\ No newline at end of file
+}
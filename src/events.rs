@@ -1,5 +1,6 @@
-use near_sdk::{near, AccountId};
+use near_sdk::{near, AccountId, PublicKey};
 use near_sdk::json_types::{U128};
+use crate::types::{ActionOutcome, StorageSponsorship};
 
 #[near(event_json(standard = "nep297"))]
 pub enum FtWrapperEvent {
@@ -10,8 +11,14 @@ pub enum FtWrapperEvent {
     #[event_version("1.0.0")]
     FtTransfer { token: AccountId, sender: AccountId, receiver: AccountId, amount: U128 },
     #[event_version("1.0.0")]
+    FtTransferFailed { token: AccountId, sender: AccountId, receiver: AccountId, amount: U128 },
+    #[event_version("1.0.0")]
     StorageDeposited { token: AccountId, account_id: AccountId, amount: U128 },
     #[event_version("1.0.0")]
+    StorageDepositFailed { token: AccountId, account_id: AccountId },
+    #[event_version("1.0.0")]
+    RegistrationsReconciled { token: AccountId, registered: Vec<AccountId>, unregistered: Vec<AccountId> },
+    #[event_version("1.0.0")]
     StorageWithdrawn { token: AccountId, account_id: AccountId, amount: U128 },
     #[event_version("1.0.0")]
     StorageUnregistered { token: AccountId, account_id: AccountId },
@@ -22,6 +29,8 @@ pub enum FtWrapperEvent {
     #[event_version("1.0.0")]
     StorageDepositUpdated { storage_deposit: U128 },
     #[event_version("1.0.0")]
+    StorageSponsorshipUpdated { policy: StorageSponsorship },
+    #[event_version("1.0.0")]
     ContractUpgraded { manager: AccountId, timestamp: u64 },
     #[event_version("1.0.0")]
     ManagerUpdated { new_manager: AccountId },
@@ -29,4 +38,56 @@ pub enum FtWrapperEvent {
     StateMigrated { old_version: String, new_version: String },
     #[event_version("1.0.0")]
     TransferFinalized { token: AccountId, recipient: AccountId, amount: U128, fee: U128, source_chain: String },
+    #[event_version("1.0.0")]
+    MpcKeyAdded { public_key: PublicKey },
+    #[event_version("1.0.0")]
+    MpcKeyRotated { old_public_key: PublicKey, new_public_key: PublicKey },
+    #[event_version("1.0.0")]
+    MpcKeyRetired { public_key: PublicKey },
+    #[event_version("1.0.0")]
+    BridgeTransferInitiated { transfer_id: u64, token: AccountId, sender: AccountId, amount: U128, destination_chain: String, recipient: String },
+    #[event_version("1.0.0")]
+    BridgeTransferCompleted { transfer_id: u64 },
+    #[event_version("1.0.0")]
+    BridgeTransferFailed { transfer_id: u64 },
+    #[event_version("1.0.0")]
+    BridgeTransferRefunded { transfer_id: u64, token: AccountId, sender: AccountId, amount: U128 },
+    #[event_version("1.0.0")]
+    BridgeRefundTimeoutUpdated { timeout_ms: u64 },
+    #[event_version("1.0.0")]
+    BridgedTokenCodeUpdated { code_hash: String },
+    #[event_version("1.0.0")]
+    BridgedTokenDeployed { token: AccountId, source_chain: String, address: String },
+    #[event_version("1.0.0")]
+    ChainAdded { chain_id: String },
+    #[event_version("1.0.0")]
+    ChainUpdated { chain_id: String },
+    #[event_version("1.0.0")]
+    ChainRemoved { chain_id: String },
+    #[event_version("1.0.0")]
+    MpcSignerUpdated { signer_contract: AccountId, key_version: u32 },
+    #[event_version("1.0.0")]
+    SignatureRequested { request_id: u64, requester: AccountId, target_chain: String, derivation_path: String },
+    #[event_version("1.0.0")]
+    SignatureIssued { request_id: u64, requester: AccountId },
+    #[event_version("1.0.0")]
+    SignatureRequestTimeoutUpdated { timeout_ms: u64 },
+    #[event_version("1.0.0")]
+    MpcRootKeyUpdated { public_key: PublicKey },
+    #[event_version("1.0.0")]
+    BitcoinTransactionSigned { spend_id: u64, txid: String },
+    #[event_version("1.0.0")]
+    ActionsExecuted { batch_id: u64, sender: AccountId, outcomes: Vec<ActionOutcome> },
+    #[event_version("1.0.0")]
+    Deposited { token: AccountId, account_id: AccountId, amount: U128 },
+    #[event_version("1.0.0")]
+    Withdrawn { token: AccountId, account_id: AccountId, receiver_id: AccountId, amount: U128 },
+    #[event_version("1.0.0")]
+    WithdrawFailed { token: AccountId, account_id: AccountId, amount: U128 },
+    #[event_version("1.0.0")]
+    IntentKeyRegistered { account_id: AccountId, public_key: PublicKey, expires_at: Option<u64>, methods: Vec<String> },
+    #[event_version("1.0.0")]
+    IntentKeyRevoked { account_id: AccountId, public_key: PublicKey },
+    #[event_version("1.0.0")]
+    DelegateActionExecuted { sender: AccountId, relayer: AccountId, nonce: u64 },
 }
\ No newline at end of file
@@ -0,0 +1,112 @@
+use near_sdk::env;
+use near_sdk::json_types::U128;
+use crate::types::{AddressFormat, EvmTransaction, Erc20Transfer, UnsignedTransaction};
+use crate::errors::FtWrapperError;
+
+/// EIP-2718 type byte of EIP-1559 transactions.
+const EIP1559_TX_TYPE: u8 = 0x02;
+/// `transfer(address,uint256)` selector.
+const ERC20_TRANSFER_SELECTOR: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+
+fn rlp_length_prefix(len: usize, short_offset: u8, long_offset: u8) -> Vec<u8> {
+    if len < 56 {
+        return vec![short_offset + len as u8];
+    }
+    let len_bytes: Vec<u8> = (len as u64).to_be_bytes().into_iter().skip_while(|b| *b == 0).collect();
+    let mut prefix = vec![long_offset + len_bytes.len() as u8];
+    prefix.extend(len_bytes);
+    prefix
+}
+
+fn rlp_bytes(bytes: &[u8]) -> Vec<u8> {
+    if bytes.len() == 1 && bytes[0] < 0x80 {
+        return bytes.to_vec();
+    }
+    let mut encoded = rlp_length_prefix(bytes.len(), 0x80, 0xb7);
+    encoded.extend_from_slice(bytes);
+    encoded
+}
+
+/// Integers are encoded as their minimal big-endian bytes; zero is the empty string.
+fn rlp_uint(value: u128) -> Vec<u8> {
+    let bytes: Vec<u8> = value.to_be_bytes().into_iter().skip_while(|b| *b == 0).collect();
+    rlp_bytes(&bytes)
+}
+
+fn rlp_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let payload = items.concat();
+    let mut encoded = rlp_length_prefix(payload.len(), 0xc0, 0xf7);
+    encoded.extend(payload);
+    encoded
+}
+
+fn parse_address(address: &str) -> Result<[u8; 20], FtWrapperError> {
+    crate::address::validate_address(AddressFormat::Evm, address)?;
+    let hex = &address[2..];
+    let mut bytes = [0u8; 20];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)
+            .map_err(|_| FtWrapperError::InvalidRecipient("EVM address must be hex".to_string()))?;
+    }
+    Ok(bytes)
+}
+
+/// Calldata of an ERC-20 `transfer(recipient, amount)`.
+fn erc20_transfer_data(recipient: &[u8; 20], amount: u128) -> Vec<u8> {
+    let mut data = ERC20_TRANSFER_SELECTOR.to_vec();
+    data.extend_from_slice(&[0u8; 12]);
+    data.extend_from_slice(recipient);
+    data.extend_from_slice(&[0u8; 16]);
+    data.extend_from_slice(&amount.to_be_bytes());
+    data
+}
+
+impl From<Erc20Transfer> for EvmTransaction {
+    fn from(transfer: Erc20Transfer) -> Self {
+        EvmTransaction {
+            chain_id: transfer.chain_id,
+            nonce: transfer.nonce,
+            max_priority_fee_per_gas: transfer.max_priority_fee_per_gas,
+            max_fee_per_gas: transfer.max_fee_per_gas,
+            gas_limit: transfer.gas_limit,
+            to: transfer.token,
+            value: U128(0),
+            data: Vec::new(),
+        }
+    }
+}
+
+/// Unsigned EIP-1559 transaction: `0x02 ‖ rlp([chain_id, nonce, max_priority_fee_per_gas,
+/// max_fee_per_gas, gas_limit, to, value, data, access_list])` with an empty access list.
+pub fn encode_eip1559(tx: &EvmTransaction) -> Result<Vec<u8>, FtWrapperError> {
+    let to = parse_address(&tx.to)?;
+    let fields = [
+        rlp_uint(tx.chain_id.into()),
+        rlp_uint(tx.nonce.into()),
+        rlp_uint(tx.max_priority_fee_per_gas.0),
+        rlp_uint(tx.max_fee_per_gas.0),
+        rlp_uint(tx.gas_limit.into()),
+        rlp_bytes(&to),
+        rlp_uint(tx.value.0),
+        rlp_bytes(&tx.data),
+        rlp_list(&[]),
+    ];
+    let mut encoded = vec![EIP1559_TX_TYPE];
+    encoded.extend(rlp_list(&fields));
+    Ok(encoded)
+}
+
+/// Encodes `transaction` and returns it together with the keccak256 hash the MPC signer signs.
+pub fn build_transaction(transaction: UnsignedTransaction) -> Result<(Vec<u8>, [u8; 32]), FtWrapperError> {
+    let tx = match transaction {
+        UnsignedTransaction::Evm(tx) => tx,
+        UnsignedTransaction::Erc20Transfer(transfer) => {
+            let data = erc20_transfer_data(&parse_address(&transfer.recipient)?, transfer.amount.0);
+            EvmTransaction { data, ..transfer.into() }
+        }
+        UnsignedTransaction::Bitcoin(_) => return Err(FtWrapperError::UnsupportedTransaction),
+    };
+    let encoded = encode_eip1559(&tx)?;
+    let hash = env::keccak256_array(&encoded);
+    Ok((encoded, hash))
+}
@@ -0,0 +1,127 @@
+use near_sdk::{env, serde_json, bs58, AccountId, Promise, PromiseResult, Gas, NearToken};
+use near_sdk::json_types::U128;
+use crate::state::FtWrapperContractState;
+use crate::types::{BridgedAsset, BridgedTokenMetadata};
+use crate::errors::FtWrapperError;
+use crate::events::FtWrapperEvent;
+use crate::ft::CALLBACK_GAS;
+use crate::{ext_bridged_token, ext_self};
+
+/// Key of `bridged_tokens`: one wrapped token per foreign asset.
+fn asset_key(source_chain: &str, address: &str) -> String {
+    format!("{}:{}", source_chain, address)
+}
+
+pub fn set_bridged_token_code(state: &mut FtWrapperContractState, code: Vec<u8>) -> Result<(), FtWrapperError> {
+    let caller = env::predecessor_account_id();
+    if !state.is_manager(&caller) {
+        return Err(FtWrapperError::Unauthorized);
+    }
+    let code_hash = bs58::encode(env::sha256_array(&code)).into_string();
+    state.bridged_token_code.set(Some(code));
+    FtWrapperEvent::BridgedTokenCodeUpdated { code_hash }.emit();
+    Ok(())
+}
+
+/// Creates `<prefix>.<wrapper>` with the attached deposit, deploys the bridged token code to it
+/// and initializes it with the wrapper as owner, so only the wrapper can mint and burn.
+pub fn deploy_bridged_token(
+    state: &mut FtWrapperContractState,
+    source_chain: String,
+    address: String,
+    prefix: String,
+    metadata: BridgedTokenMetadata,
+) -> Result<Promise, FtWrapperError> {
+    let caller = env::predecessor_account_id();
+    if !state.is_manager(&caller) {
+        return Err(FtWrapperError::Unauthorized);
+    }
+    if state.bridged_tokens.contains_key(&asset_key(&source_chain, &address)) {
+        return Err(FtWrapperError::BridgedTokenExists);
+    }
+    let token: AccountId = format!("{}.{}", prefix, env::current_account_id())
+        .parse()
+        .map_err(|_| FtWrapperError::InvalidAccountPrefix)?;
+    if prefix.contains('.') || state.bridged_token_origins.contains_key(&token) {
+        return Err(FtWrapperError::InvalidAccountPrefix);
+    }
+    let code = state.bridged_token_code.get().clone().ok_or(FtWrapperError::BridgedTokenCodeMissing)?;
+
+    let init_args = serde_json::json!({
+        "owner_id": env::current_account_id(),
+        "metadata": metadata,
+    });
+    Ok(Promise::new(token.clone())
+        .create_account()
+        .transfer(env::attached_deposit())
+        .deploy_contract(code)
+        .function_call(
+            "new".to_string(),
+            init_args.to_string().into_bytes(),
+            NearToken::from_yoctonear(0),
+            Gas::from_gas(state.cross_contract_gas),
+        )
+        .then(
+            ext_self::ext(env::current_account_id())
+                .with_static_gas(CALLBACK_GAS)
+                .handle_bridged_token_deployed(source_chain, address, token),
+        ))
+}
+
+pub fn handle_bridged_token_deployed(
+    state: &mut FtWrapperContractState,
+    source_chain: String,
+    address: String,
+    token: AccountId,
+) -> bool {
+    if !matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+        env::log_str(&format!("Deployment of bridged token {} failed", token));
+        return false;
+    }
+    state.bridged_tokens.insert(asset_key(&source_chain, &address), token.clone());
+    state.bridged_token_origins.insert(token.clone(), BridgedAsset {
+        source_chain: source_chain.clone(),
+        address: address.clone(),
+    });
+    if !state.supported_tokens.contains(&token) {
+        state.supported_tokens.push(token.clone());
+    }
+    FtWrapperEvent::BridgedTokenDeployed { token, source_chain, address }.emit();
+    true
+}
+
+/// Mints inbound bridged tokens. Fails unless `token` was deployed for `source_chain`.
+pub fn mint(
+    state: &FtWrapperContractState,
+    token: &AccountId,
+    source_chain: &str,
+    recipient: AccountId,
+    amount: U128,
+) -> Result<Promise, FtWrapperError> {
+    match state.bridged_token_origins.get(token) {
+        Some(origin) if origin.source_chain == source_chain => {}
+        _ => return Err(FtWrapperError::TokenNotBridged),
+    }
+    Ok(ext_bridged_token::ext(token.clone())
+        .with_static_gas(Gas::from_gas(state.cross_contract_gas))
+        .mint(recipient, amount))
+}
+
+/// Burns bridged tokens held by the wrapper once they have left NEAR.
+pub fn burn(state: &FtWrapperContractState, token: AccountId, amount: U128) -> Promise {
+    ext_bridged_token::ext(token)
+        .with_static_gas(Gas::from_gas(state.cross_contract_gas))
+        .burn(env::current_account_id(), amount)
+}
+
+pub fn is_bridged_token(state: &FtWrapperContractState, token: &AccountId) -> bool {
+    state.bridged_token_origins.contains_key(token)
+}
+
+pub fn get_bridged_token(state: &FtWrapperContractState, source_chain: String, address: String) -> Option<AccountId> {
+    state.bridged_tokens.get(&asset_key(&source_chain, &address)).cloned()
+}
+
+pub fn get_bridged_token_origin(state: &FtWrapperContractState, token: AccountId) -> Option<BridgedAsset> {
+    state.bridged_token_origins.get(&token).cloned()
+}
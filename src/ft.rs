@@ -1,12 +1,21 @@
-use near_sdk::{env, AccountId, Promise, Gas, NearToken};
+use near_sdk::{env, serde_json, AccountId, CurveType, Promise, PromiseOrValue, PromiseResult, PublicKey, Gas, NearToken};
 use near_sdk::json_types::U128;
+use near_sdk::borsh;
+use near_sdk::store::IterableSet;
 use crate::state::FtWrapperContractState;
-use crate::types::{FtTransferArgs, RequestChainSignatureArgs, BridgeTransferArgs, StorageBalance, FinalizeTransferArgs};
+use crate::types::{FtTransferArgs, BridgeTransferArgs, StorageBalance, FinalizeTransferArgs, BridgeMessage, BridgeTransfer, BridgeTransferMsg, BridgeTransferStatus, Action, RegistrationStatus, RelayOperation, StorageSponsorship, RelayResponse, BRIDGE_MESSAGE_VERSION};
 use crate::errors::FtWrapperError;
 use crate::events::FtWrapperEvent;
-use crate::{ext_ft, ext_self};
+use crate::{ext_ft, ext_relayer, ext_self};
 
-pub fn ft_transfer(state: &mut FtWrapperContractState, args: FtTransferArgs) -> Result<Promise, FtWrapperError> {
+/// Page size used by listing views when the caller does not pass a `limit`.
+pub(crate) const DEFAULT_PAGE_LIMIT: u64 = 50;
+/// Static gas for callbacks that only update local state.
+pub(crate) const CALLBACK_GAS: Gas = Gas::from_tgas(10);
+
+/// Sends `args.amount` of the tokens `sender_id` holds in the wrapper to `args.receiver_id`.
+/// The amount is debited up front and credited back if the token transfer fails.
+pub fn ft_transfer(state: &mut FtWrapperContractState, sender_id: AccountId, args: FtTransferArgs) -> Result<Promise, FtWrapperError> {
     state.assert_balance()?;
     if !state.supported_tokens.contains(&args.token) {
         return Err(FtWrapperError::TokenNotSupported);
@@ -14,61 +23,241 @@ pub fn ft_transfer(state: &mut FtWrapperContractState, args: FtTransferArgs) ->
     if args.amount.0 == 0 {
         return Err(FtWrapperError::AmountTooLow);
     }
+    if balance_of(state, &args.token, &sender_id) < args.amount.0 {
+        return Err(FtWrapperError::InsufficientBalance);
+    }
 
-    let sender_id = env::predecessor_account_id();
-    
-    let sender_promise = ensure_registered(state, args.token.clone(), sender_id.clone())?;
+    // The sender holds a ledger balance, so only the receiver may need registering
     let receiver_promise = ensure_registered(state, args.token.clone(), args.receiver_id.clone())?;
-    
+    debit(state, &args.token, &sender_id, args.amount.0);
+
     let transfer_promise = ext_ft::ext(args.token.clone())
-        .with_static_gas(Gas::from_tgas(state.cross_contract_gas))
+        .with_static_gas(Gas::from_gas(state.cross_contract_gas))
+        .with_attached_deposit(NearToken::from_yoctonear(1))
         .ft_transfer(args.receiver_id.clone(), args.amount, args.memo.clone());
 
-    FtWrapperEvent::FtTransfer {
-        token: args.token,
-        sender: sender_id,
-        receiver: args.receiver_id,
-        amount: args.amount,
-    }.emit();
+    Ok(receiver_promise.then(transfer_promise).then(
+        ext_self::ext(env::current_account_id())
+            .with_static_gas(CALLBACK_GAS)
+            .handle_ft_transfer(args.token, sender_id, args.receiver_id, args.amount),
+    ))
+}
 
-    Ok(sender_promise.and(receiver_promise).then(transfer_promise))
+/// Callback for the token transfer made by [`ft_transfer`]. `FtTransfer` is only emitted once
+/// the token has moved the tokens; a failed transfer credits the sender back.
+pub fn handle_ft_transfer(
+    state: &mut FtWrapperContractState,
+    token: AccountId,
+    sender_id: AccountId,
+    receiver_id: AccountId,
+    amount: U128,
+) -> bool {
+    if matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+        FtWrapperEvent::FtTransfer { token, sender: sender_id, receiver: receiver_id, amount }.emit();
+        return true;
+    }
+    credit(state, &token, &sender_id, amount.0);
+    FtWrapperEvent::FtTransferFailed { token, sender: sender_id, receiver: receiver_id, amount }.emit();
+    false
 }
 
-pub fn ensure_registered(state: &mut FtWrapperContractState, token: AccountId, account_id: AccountId) -> Result<Promise, FtWrapperError> {
+/// Sends `amount` of `sender_id`'s deposited tokens to `receiver_id` through the token's
+/// `ft_transfer_call`. The amount is debited up front; [`handle_ft_transfer_call`] credits
+/// back what the receiver did not use.
+pub fn ft_transfer_call(
+    state: &mut FtWrapperContractState,
+    sender_id: AccountId,
+    token: AccountId,
+    receiver_id: AccountId,
+    amount: U128,
+    memo: Option<String>,
+    msg: String,
+) -> Result<Promise, FtWrapperError> {
     state.assert_balance()?;
     if !state.supported_tokens.contains(&token) {
         return Err(FtWrapperError::TokenNotSupported);
     }
+    if amount.0 == 0 {
+        return Err(FtWrapperError::AmountTooLow);
+    }
+    if balance_of(state, &token, &sender_id) < amount.0 {
+        return Err(FtWrapperError::InsufficientBalance);
+    }
 
-    let storage_balance = state.storage_balances.get(&(token.clone(), account_id.clone()));
+    let receiver_promise = ensure_registered(state, token.clone(), receiver_id.clone())?;
+    debit(state, &token, &sender_id, amount.0);
+
+    // The receiver's ft_on_transfer and the token's resolve step run inside this call
+    let transfer_promise = ext_ft::ext(token.clone())
+        .with_static_gas(Gas::from_gas(state.cross_contract_gas))
+        .with_attached_deposit(NearToken::from_yoctonear(1))
+        .with_unused_gas_weight(1)
+        .ft_transfer_call(receiver_id.clone(), amount, memo, msg);
+    Ok(receiver_promise.then(transfer_promise).then(
+        ext_self::ext(env::current_account_id())
+            .with_static_gas(CALLBACK_GAS)
+            .handle_ft_transfer_call(token, sender_id, receiver_id, amount),
+    ))
+}
+
+/// Resolve callback for [`ft_transfer_call`]. The token returns the amount the receiver used;
+/// the rest is back with the wrapper and is credited to the sender. Returns the used amount.
+pub fn handle_ft_transfer_call(
+    state: &mut FtWrapperContractState,
+    token: AccountId,
+    sender_id: AccountId,
+    receiver_id: AccountId,
+    amount: U128,
+) -> U128 {
+    // A failed call moved nothing
+    let used = match env::promise_result(0) {
+        PromiseResult::Successful(value) => serde_json::from_slice::<U128>(&value)
+            .map(|used| used.0.min(amount.0))
+            .unwrap_or(amount.0),
+        _ => 0,
+    };
+    let unused = amount.0 - used;
+    if unused > 0 {
+        credit(state, &token, &sender_id, unused);
+    }
+    if used > 0 {
+        FtWrapperEvent::FtTransfer { token, sender: sender_id, receiver: receiver_id, amount: U128(used) }.emit();
+    }
+    U128(used)
+}
+
+/// Sends `amount` of the caller's deposited tokens out to `receiver_id`, or to the caller.
+/// The amount is debited up front and credited back if the token transfer fails.
+pub fn withdraw(state: &mut FtWrapperContractState, token: AccountId, amount: U128, receiver_id: Option<AccountId>) -> Result<Promise, FtWrapperError> {
+    state.assert_balance()?;
+    if amount.0 == 0 {
+        return Err(FtWrapperError::AmountTooLow);
+    }
+    // Balances of tokens that were removed since can still be taken out
+    let account_id = env::predecessor_account_id();
+    if balance_of(state, &token, &account_id) < amount.0 {
+        return Err(FtWrapperError::InsufficientBalance);
+    }
+    let receiver_id = receiver_id.unwrap_or_else(|| account_id.clone());
+
+    let receiver_promise = register_receiver(state, token.clone(), receiver_id.clone())?;
+    debit(state, &token, &account_id, amount.0);
+
+    let transfer_promise = ext_ft::ext(token.clone())
+        .with_static_gas(Gas::from_gas(state.cross_contract_gas))
+        .with_attached_deposit(NearToken::from_yoctonear(1))
+        .ft_transfer(receiver_id.clone(), amount, Some("Withdrawal from ft-wrapper".to_string()));
+    Ok(receiver_promise.then(transfer_promise).then(
+        ext_self::ext(env::current_account_id())
+            .with_static_gas(CALLBACK_GAS)
+            .handle_withdraw(token, account_id, receiver_id, amount),
+    ))
+}
 
-    if storage_balance.is_some() {
-        Ok(Promise::new(env::current_account_id()))
+/// Callback for the token transfer made by [`withdraw`]: credits the account back if it failed.
+pub fn handle_withdraw(
+    state: &mut FtWrapperContractState,
+    token: AccountId,
+    account_id: AccountId,
+    receiver_id: AccountId,
+    amount: U128,
+) -> bool {
+    if matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+        FtWrapperEvent::Withdrawn { token, account_id, receiver_id, amount }.emit();
+        return true;
+    }
+    credit(state, &token, &account_id, amount.0);
+    FtWrapperEvent::WithdrawFailed { token, account_id, amount }.emit();
+    false
+}
+
+/// Tokens `account_id` holds in the wrapper's ledger.
+pub fn balance_of(state: &FtWrapperContractState, token: &AccountId, account_id: &AccountId) -> u128 {
+    state.balances.get(&(token.clone(), account_id.clone())).copied().unwrap_or(0)
+}
+
+pub fn credit(state: &mut FtWrapperContractState, token: &AccountId, account_id: &AccountId, amount: u128) {
+    let balance = balance_of(state, token, account_id);
+    state.balances.insert((token.clone(), account_id.clone()), balance + amount);
+}
+
+/// Callers check the balance first; emptied entries are removed.
+pub fn debit(state: &mut FtWrapperContractState, token: &AccountId, account_id: &AccountId, amount: u128) {
+    let balance = balance_of(state, token, account_id) - amount;
+    if balance == 0 {
+        state.balances.remove(&(token.clone(), account_id.clone()));
     } else {
-        let deposit_amount = state.storage_deposit.0;
-        let contract_balance = env::account_balance().as_yoctonear();
-        if contract_balance < deposit_amount {
-            FtWrapperEvent::LowBalance { balance: contract_balance }.emit();
-            return Err(FtWrapperError::LowBalance);
-        }
-        let deposit_promise = ext_ft::ext(token.clone())
-            .with_static_gas(Gas::from_tgas(state.cross_contract_gas))
-            .with_attached_deposit(NearToken::from_yoctonear(deposit_amount))
-            .storage_deposit(Some(account_id.clone()), Some(true))
-            .then(
-                ext_self::ext(env::current_account_id())
-                    .with_static_gas(Gas::from_tgas(state.cross_contract_gas))
-                    .handle_storage_deposit(token.clone(), account_id.clone()),
-            );
+        state.balances.insert((token.clone(), account_id.clone()), balance);
+    }
+}
 
-        FtWrapperEvent::StorageDeposited {
-            token: token.clone(),
-            account_id: account_id.clone(),
-            amount: U128(deposit_amount),
-        }.emit();
+pub fn get_registration_status(state: &FtWrapperContractState, token: AccountId, account_id: AccountId) -> Option<RegistrationStatus> {
+    registration_status(state, &token, &account_id)
+}
+
+pub fn get_balance(state: &FtWrapperContractState, token: AccountId, account_id: AccountId) -> U128 {
+    U128(balance_of(state, &token, &account_id))
+}
 
-        Ok(deposit_promise)
+/// Registers `account_id` on `token` at the wrapper's expense, if the sponsorship policy
+/// allows it.
+pub fn ensure_registered(state: &mut FtWrapperContractState, token: AccountId, account_id: AccountId) -> Result<Promise, FtWrapperError> {
+    state.assert_balance()?;
+    if !state.supported_tokens.contains(&token) {
+        return Err(FtWrapperError::TokenNotSupported);
+    }
+    register_receiver(state, token, account_id)
+}
+
+/// [`ensure_registered`] without the supported token check, for tokens already in the ledger.
+fn register_receiver(state: &mut FtWrapperContractState, token: AccountId, account_id: AccountId) -> Result<Promise, FtWrapperError> {
+    if matches!(
+        registration_status(state, &token, &account_id),
+        Some(RegistrationStatus::Registered | RegistrationStatus::Pending)
+    ) {
+        return Ok(Promise::new(env::current_account_id()));
+    }
+    if state.storage_sponsorship != StorageSponsorship::Sponsored {
+        return Err(FtWrapperError::AccountNotRegistered);
     }
+    let contract_balance = env::account_balance().as_yoctonear();
+    if contract_balance < state.storage_deposit.0 {
+        FtWrapperEvent::LowBalance { balance: contract_balance }.emit();
+        return Err(FtWrapperError::LowBalance);
+    }
+    // Sponsored registrations are recorded as nothing paid, so unregistering refunds nothing
+    let balance = StorageBalance { total: U128(0), available: U128(0) };
+    Ok(start_registration(state, token, account_id, env::current_account_id(), balance))
+}
+
+/// Registration state of `account_id` on `token`. Records made before registrations were
+/// tracked only exist in `storage_balances` and count as registered.
+pub fn registration_status(state: &FtWrapperContractState, token: &AccountId, account_id: &AccountId) -> Option<RegistrationStatus> {
+    let key = (token.clone(), account_id.clone());
+    state.registrations.get(&key).copied()
+        .or_else(|| state.storage_balances.contains_key(&key).then_some(RegistrationStatus::Registered))
+}
+
+/// Pays the token's minimum storage deposit for `account_id`. `balance` is what `payer` put
+/// in and is recorded once the token accepts the deposit, or refunded to `payer` if it does not.
+fn start_registration(
+    state: &mut FtWrapperContractState,
+    token: AccountId,
+    account_id: AccountId,
+    payer: AccountId,
+    balance: StorageBalance,
+) -> Promise {
+    let deposit_amount = state.storage_deposit;
+    state.registrations.insert((token.clone(), account_id.clone()), RegistrationStatus::Pending);
+    ext_ft::ext(token.clone())
+        .with_static_gas(Gas::from_gas(state.cross_contract_gas))
+        .with_attached_deposit(NearToken::from_yoctonear(deposit_amount.0))
+        .storage_deposit(Some(account_id.clone()), Some(true))
+        .then(
+            ext_self::ext(env::current_account_id())
+                .with_static_gas(CALLBACK_GAS)
+                .handle_storage_deposit(token, account_id, deposit_amount, payer, balance),
+        )
 }
 
 pub fn ft_balance_of(state: &FtWrapperContractState, token: AccountId, account_id: AccountId) -> Promise {
@@ -76,10 +265,14 @@ pub fn ft_balance_of(state: &FtWrapperContractState, token: AccountId, account_i
         env::panic_str("Token not supported");
     }
     ext_ft::ext(token)
-        .with_static_gas(Gas::from_tgas(state.cross_contract_gas))
+        .with_static_gas(Gas::from_gas(state.cross_contract_gas))
         .ft_balance_of(account_id)
 }
 
+/// NEP-145 `storage_deposit` for `account_id` on `token`. The attached deposit must cover the
+/// token's minimum; the excess is refunded when `registration_only` is set and kept as
+/// `available` otherwise. Without an attached deposit the wrapper pays, if its sponsorship
+/// policy allows it.
 pub fn storage_deposit(
     state: &mut FtWrapperContractState,
     token: AccountId,
@@ -91,49 +284,54 @@ pub fn storage_deposit(
         return Err(FtWrapperError::TokenNotSupported);
     }
 
-    let account_id = account_id.unwrap_or_else(|| env::predecessor_account_id());
+    let payer = env::predecessor_account_id();
+    let account_id = account_id.unwrap_or_else(|| payer.clone());
     let registration_only = registration_only.unwrap_or(false);
+    let attached = env::attached_deposit().as_yoctonear();
+    let key = (token.clone(), account_id.clone());
 
-    let storage_balance = state.storage_balances.get(&(token.clone(), account_id.clone()));
-
-    if let Some(balance) = storage_balance {
-        return Ok(balance.clone());
+    match registration_status(state, &token, &account_id) {
+        Some(RegistrationStatus::Registered) => {
+            let mut balance = state.storage_balances.get(&key).cloned()
+                .unwrap_or(StorageBalance { total: U128(0), available: U128(0) });
+            if registration_only {
+                if attached > 0 {
+                    Promise::new(payer).transfer(NearToken::from_yoctonear(attached));
+                }
+            } else if attached > 0 {
+                balance.total.0 += attached;
+                balance.available.0 += attached;
+                state.storage_balances.insert(key, balance.clone());
+                FtWrapperEvent::StorageDeposited { token, account_id, amount: U128(attached) }.emit();
+            }
+            return Ok(balance);
+        }
+        Some(RegistrationStatus::Pending) => return Err(FtWrapperError::RegistrationPending),
+        _ => {}
     }
 
-    let deposit_amount = state.storage_deposit.0;
-    let contract_balance = env::account_balance().as_yoctonear();
-    if contract_balance < deposit_amount {
-        FtWrapperEvent::LowBalance { balance: contract_balance }.emit();
-        return Err(FtWrapperError::LowBalance);
+    // The balance is recorded once the token has accepted the deposit
+    if attached == 0 {
+        if state.storage_sponsorship != StorageSponsorship::Sponsored {
+            return Err(FtWrapperError::InvalidDeposit);
+        }
+        ensure_registered(state, token, account_id)?;
+        return Ok(StorageBalance { total: U128(0), available: U128(0) });
     }
-
-    let deposit_promise = ext_ft::ext(token.clone())
-        .with_static_gas(Gas::from_tgas(state.cross_contract_gas))
-        .with_attached_deposit(NearToken::from_yoctonear(deposit_amount))
-        .storage_deposit(Some(account_id.clone()), Some(registration_only))
-        .then(
-            ext_self::ext(env::current_account_id())
-                .with_static_gas(Gas::from_tgas(state.cross_contract_gas))
-                .handle_storage_deposit(token.clone(), account_id.clone()),
-        );
-
-    state.storage_balances.insert(
-        (token.clone(), account_id.clone()),
-        StorageBalance { total: U128(deposit_amount), available: U128(0) },
-    );
-
-    FtWrapperEvent::StorageDeposited {
-        token,
-        account_id,
-        amount: U128(deposit_amount),
-    }.emit();
-
-    deposit_promise.then(Promise::new(env::current_account_id()));
-
-    Ok(StorageBalance {
-        total: U128(deposit_amount),
-        available: U128(0),
-    })
+    let minimum = state.storage_deposit.0;
+    if attached < minimum {
+        return Err(FtWrapperError::InsufficientStorageBalance);
+    }
+    let balance = if registration_only {
+        if attached > minimum {
+            Promise::new(payer.clone()).transfer(NearToken::from_yoctonear(attached - minimum));
+        }
+        StorageBalance { total: U128(minimum), available: U128(0) }
+    } else {
+        StorageBalance { total: U128(attached), available: U128(attached - minimum) }
+    };
+    start_registration(state, token, account_id, payer, balance.clone());
+    Ok(balance)
 }
 
 pub fn storage_withdraw(
@@ -181,19 +379,96 @@ pub fn storage_balance_of(state: &FtWrapperContractState, token: AccountId, acco
         env::panic_str("Token not supported");
     }
     ext_ft::ext(token)
-        .with_static_gas(Gas::from_tgas(state.cross_contract_gas))
+        .with_static_gas(Gas::from_gas(state.cross_contract_gas))
         .storage_balance_of(account_id)
 }
 
+/// Largest number of accounts `reconcile_registrations` queries in one call.
+const MAX_RECONCILIATION_BATCH: usize = 10;
+/// Static gas for a `storage_balance_of` query, which only reads the token's state.
+const VIEW_GAS: Gas = Gas::from_tgas(5);
+
+/// Queries the token's `storage_balance_of` for every account in one joint promise and lets
+/// [`handle_reconciliation`] fix the local records that disagree.
+pub fn reconcile_registrations(state: &mut FtWrapperContractState, token: AccountId, accounts: Vec<AccountId>) -> Result<Promise, FtWrapperError> {
+    let caller = env::predecessor_account_id();
+    if !state.is_manager(&caller) {
+        return Err(FtWrapperError::Unauthorized);
+    }
+    if !state.supported_tokens.contains(&token) {
+        return Err(FtWrapperError::TokenNotSupported);
+    }
+    if accounts.is_empty() || accounts.len() > MAX_RECONCILIATION_BATCH {
+        return Err(FtWrapperError::InvalidReconciliationBatch);
+    }
+    let queries = accounts.iter()
+        .map(|account_id| {
+            ext_ft::ext(token.clone())
+                .with_static_gas(VIEW_GAS)
+                .storage_balance_of(account_id.clone())
+        })
+        .reduce(|joint, query| joint.and(query))
+        .expect("At least one account");
+    Ok(queries.then(
+        ext_self::ext(env::current_account_id())
+            .with_static_gas(CALLBACK_GAS)
+            .handle_reconciliation(token, accounts),
+    ))
+}
+
+/// Result `i` answers for `accounts[i]`. Accounts whose query failed, or whose deposit is
+/// still in flight, are left alone.
+pub fn handle_reconciliation(state: &mut FtWrapperContractState, token: AccountId, accounts: Vec<AccountId>) {
+    let mut registered = Vec::new();
+    let mut unregistered = Vec::new();
+    for (index, account_id) in accounts.into_iter().enumerate() {
+        let on_token = match env::promise_result(index as u64) {
+            PromiseResult::Successful(value) => match serde_json::from_slice::<Option<StorageBalance>>(&value) {
+                Ok(balance) => balance,
+                Err(_) => continue,
+            },
+            _ => continue,
+        };
+        let key = (token.clone(), account_id.clone());
+        match (registration_status(state, &token, &account_id), on_token) {
+            (Some(RegistrationStatus::Pending), _) | (Some(RegistrationStatus::Registered), Some(_)) => {}
+            (_, Some(_)) => {
+                // The token's balance was paid by someone else; crediting it here would let the
+                // account withdraw NEAR it never deposited
+                state.registrations.insert(key.clone(), RegistrationStatus::Registered);
+                state.storage_balances.entry(key)
+                    .or_insert(StorageBalance { total: U128(0), available: U128(0) });
+                registered.push(account_id);
+            }
+            (Some(RegistrationStatus::Registered), None) => {
+                state.registrations.remove(&key);
+                // The token refunds the minimum itself when an account unregisters there; only
+                // the part the wrapper kept is paid back
+                if let Some(balance) = state.storage_balances.remove(&key) {
+                    if balance.available.0 > 0 {
+                        Promise::new(account_id.clone()).transfer(NearToken::from_yoctonear(balance.available.0));
+                    }
+                }
+                unregistered.push(account_id);
+            }
+            (_, None) => {}
+        }
+    }
+    FtWrapperEvent::RegistrationsReconciled { token, registered, unregistered }.emit();
+}
+
 pub fn storage_balance_bounds(state: &FtWrapperContractState, token: AccountId) -> Promise {
     if !state.supported_tokens.contains(&token) {
         env::panic_str("Token not supported");
     }
     ext_ft::ext(token)
-        .with_static_gas(Gas::from_tgas(state.cross_contract_gas))
+        .with_static_gas(Gas::from_gas(state.cross_contract_gas))
         .storage_balance_bounds()
 }
 
+/// Drops the caller's registration record for `token` and refunds its `available` balance.
+/// The minimum forwarded to the token stays there, along with the account's registration on
+/// the token. Without `force` the token balance is checked to be zero first.
 pub fn storage_unregister(
     state: &mut FtWrapperContractState,
     token: AccountId,
@@ -216,21 +491,22 @@ pub fn storage_unregister(
 
     if !force {
         ext_ft::ext(token.clone())
-            .with_static_gas(Gas::from_tgas(state.cross_contract_gas))
+            .with_static_gas(Gas::from_gas(state.cross_contract_gas))
             .ft_balance_of(account_id.clone())
             .then(
                 ext_self::ext(env::current_account_id())
-                    .with_static_gas(Gas::from_tgas(state.cross_contract_gas))
+                    .with_static_gas(Gas::from_gas(state.cross_contract_gas))
                     .handle_balance_check(token.clone(), account_id.clone()),
             );
         return Ok(false);
     }
 
-    if balance.total.0 > 0 {
-        Promise::new(account_id.clone()).transfer(NearToken::from_yoctonear(balance.total.0));
+    if balance.available.0 > 0 {
+        Promise::new(account_id.clone()).transfer(NearToken::from_yoctonear(balance.available.0));
     }
 
     state.storage_balances.remove(&(token.clone(), account_id.clone()));
+    state.registrations.remove(&(token.clone(), account_id.clone()));
 
     FtWrapperEvent::StorageUnregistered { token, account_id }.emit();
 
@@ -250,10 +526,11 @@ pub fn handle_balance_check(
 
     let storage_balance = state.storage_balances.get(&(token.clone(), account_id.clone()));
     if let Some(balance) = storage_balance {
-        if balance.total.0 > 0 {
-            Promise::new(account_id.clone()).transfer(NearToken::from_yoctonear(balance.total.0));
+        if balance.available.0 > 0 {
+            Promise::new(account_id.clone()).transfer(NearToken::from_yoctonear(balance.available.0));
         }
         state.storage_balances.remove(&(token.clone(), account_id.clone()));
+        state.registrations.remove(&(token.clone(), account_id.clone()));
         FtWrapperEvent::StorageUnregistered { token, account_id }.emit();
         return true;
     }
@@ -266,60 +543,83 @@ pub fn handle_registration(
     token: AccountId,
     account_id: AccountId,
 ) -> Promise {
-    let storage_balance = state.storage_balances.get(&(token.clone(), account_id.clone()));
-    if storage_balance.is_none() {
-        let bounds_promise = ext_ft::ext(token.clone())
-            .with_static_gas(Gas::from_tgas(state.cross_contract_gas))
-            .storage_balance_bounds()
-            .then(
-                ext_self::ext(env::current_account_id())
-                    .with_static_gas(Gas::from_tgas(state.cross_contract_gas))
-                    .handle_storage_deposit(token.clone(), account_id.clone()),
-            );
-        bounds_promise
-    } else {
-        Promise::new(env::current_account_id())
-    }
+    ensure_registered(state, token, account_id).unwrap_or_else(|error| env::panic_str(&error.to_string()))
 }
 
+/// Callback for the token's `storage_deposit`: records the registration only once the token
+/// has accepted it. A rejected deposit comes back to the wrapper; what `payer` paid is refunded
+/// and the registration can be paid again.
 pub fn handle_storage_deposit(
     state: &mut FtWrapperContractState,
     token: AccountId,
     account_id: AccountId,
-) -> Promise {
-    state.assert_balance().unwrap_or_else(|_| env::panic_str("Low balance"));
-    let deposit_amount = state.storage_deposit.0;
-    let deposit_promise = ext_ft::ext(token.clone())
-        .with_static_gas(Gas::from_tgas(state.cross_contract_gas))
-        .with_attached_deposit(NearToken::from_yoctonear(deposit_amount))
-        .storage_deposit(Some(account_id.clone()), Some(true));
-    
-    FtWrapperEvent::StorageDeposited {
-        token,
-        account_id,
-        amount: U128(deposit_amount),
-    }.emit();
+    amount: U128,
+    payer: AccountId,
+    balance: StorageBalance,
+) -> bool {
+    let key = (token.clone(), account_id.clone());
+    if !matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+        state.registrations.insert(key, RegistrationStatus::Failed);
+        if balance.total.0 > 0 {
+            Promise::new(payer).transfer(NearToken::from_yoctonear(balance.total.0));
+        }
+        FtWrapperEvent::StorageDepositFailed { token, account_id }.emit();
+        return false;
+    }
+    state.registrations.insert(key.clone(), RegistrationStatus::Registered);
+    if !state.storage_balances.contains_key(&key) {
+        state.storage_balances.insert(key, balance);
+    }
+    FtWrapperEvent::StorageDeposited { token, account_id, amount }.emit();
+    true
+}
 
-    deposit_promise
+/// NEP-141 receiver: the tokens in `amount` have already been moved to the wrapper by the
+/// calling token contract. An empty `msg` credits them to `sender_id`'s balance; a bridge
+/// message locks them behind an outbound bridge transfer instead.
+pub fn ft_on_transfer(
+    state: &mut FtWrapperContractState,
+    sender_id: AccountId,
+    amount: U128,
+    msg: String,
+) -> Result<PromiseOrValue<U128>, FtWrapperError> {
+    let token = env::predecessor_account_id();
+    if msg.is_empty() {
+        if !state.supported_tokens.contains(&token) {
+            return Err(FtWrapperError::TokenNotSupported);
+        }
+        credit(state, &token, &sender_id, amount.0);
+        FtWrapperEvent::Deposited { token, account_id: sender_id, amount }.emit();
+        return Ok(PromiseOrValue::Value(U128(0)));
+    }
+    let transfer_msg: BridgeTransferMsg = serde_json::from_str(&msg)
+        .map_err(|_| FtWrapperError::InvalidTransferMessage)?;
+    let args = BridgeTransferArgs {
+        token,
+        amount,
+        destination_chain: transfer_msg.destination_chain,
+        recipient: transfer_msg.recipient,
+    };
+    // The relay runs detached; all received tokens stay locked behind the transfer record
+    bridge_transfer(state, sender_id, args)?;
+    Ok(PromiseOrValue::Value(U128(0)))
 }
 
-pub fn request_chain_signature(state: &mut FtWrapperContractState, args: RequestChainSignatureArgs) -> Result<Promise, FtWrapperError> {
-    state.assert_balance()?;
-    if !state.supported_tokens.contains(&args.token) {
-        return Err(FtWrapperError::TokenNotSupported);
+/// Bridges tokens out of `sender_id`'s balance. The amount moves from the balance into the
+/// locked total; refunds of the transfer are paid out to the sender's account on the token.
+pub fn bridge_from_balance(state: &mut FtWrapperContractState, sender_id: AccountId, args: BridgeTransferArgs) -> Result<Promise, FtWrapperError> {
+    if balance_of(state, &args.token, &sender_id) < args.amount.0 {
+        return Err(FtWrapperError::InsufficientBalance);
     }
-    let sender_id = env::predecessor_account_id();
-    let promise = ensure_registered(state, args.token.clone(), sender_id)?;
-    Ok(promise.then(Promise::new(state.relayer_contract.clone())
-        .function_call(
-            "relay_meta_transaction".to_string(),
-            vec![],
-            NearToken::from_yoctonear(0),
-            Gas::from_tgas(state.cross_contract_gas),
-        )))
+    let (token, amount) = (args.token.clone(), args.amount.0);
+    let promise = bridge_transfer(state, sender_id.clone(), args)?;
+    debit(state, &token, &sender_id, amount);
+    Ok(promise)
 }
 
-pub fn bridge_transfer(state: &mut FtWrapperContractState, args: BridgeTransferArgs) -> Result<Promise, FtWrapperError> {
+/// Locks `args.amount` of tokens already held for `sender_id`, records the outbound transfer
+/// and hands it to the relayer.
+pub fn bridge_transfer(state: &mut FtWrapperContractState, sender_id: AccountId, args: BridgeTransferArgs) -> Result<Promise, FtWrapperError> {
     state.assert_balance()?;
     if !state.supported_tokens.contains(&args.token) {
         return Err(FtWrapperError::TokenNotSupported);
@@ -327,15 +627,259 @@ pub fn bridge_transfer(state: &mut FtWrapperContractState, args: BridgeTransferA
     if args.amount.0 == 0 {
         return Err(FtWrapperError::AmountTooLow);
     }
-    let sender_id = env::predecessor_account_id();
-    let promise = ensure_registered(state, args.token.clone(), sender_id)?;
-    Ok(promise.then(Promise::new(state.relayer_contract.clone())
-        .function_call(
-            "relay_meta_transaction".to_string(),
-            vec![],
-            NearToken::from_yoctonear(0),
-            Gas::from_tgas(state.cross_contract_gas),
-        )))
+    crate::chains::assert_outbound_transfer(state, &args.destination_chain, &args.recipient, args.amount)?;
+
+    let locked = state.locked_balances.get(&args.token).copied().unwrap_or(0);
+    state.locked_balances.insert(args.token.clone(), locked + args.amount.0);
+
+    let transfer_id = state.next_bridge_transfer_id;
+    state.next_bridge_transfer_id += 1;
+    let now = env::block_timestamp_ms();
+    state.bridge_transfers.insert(transfer_id, BridgeTransfer {
+        id: transfer_id,
+        sender: sender_id.clone(),
+        token: args.token.clone(),
+        amount: args.amount,
+        destination_chain: args.destination_chain.clone(),
+        recipient: args.recipient.clone(),
+        status: BridgeTransferStatus::Pending,
+        created_at: now,
+        updated_at: now,
+    });
+    state.bridge_transfers_by_sender
+        .entry(sender_id.clone())
+        .or_default()
+        .push(transfer_id);
+    index_transfer_status(state, transfer_id, BridgeTransferStatus::Pending);
+
+    FtWrapperEvent::BridgeTransferInitiated {
+        transfer_id,
+        token: args.token.clone(),
+        sender: sender_id.clone(),
+        amount: args.amount,
+        destination_chain: args.destination_chain.clone(),
+        recipient: args.recipient.clone(),
+    }.emit();
+
+    let actions = vec![Action::BridgeTransfer {
+        token: args.token,
+        amount: args.amount,
+        destination_chain: args.destination_chain,
+        recipient: args.recipient,
+    }];
+    Ok(ext_relayer::ext(state.relayer_contract.clone())
+        .with_static_gas(Gas::from_gas(state.cross_contract_gas))
+        .relay_meta_transaction(RelayOperation::BridgeTransfer { transfer_id }, sender_id, actions)
+        .then(
+            ext_self::ext(env::current_account_id())
+                .with_static_gas(relay_callback_gas(state))
+                .handle_bridge_relay(transfer_id),
+        ))
+}
+
+/// The relay callback may start a refund, so it needs room for a transfer and its callback.
+pub(crate) fn relay_callback_gas(state: &FtWrapperContractState) -> Gas {
+    Gas::from_gas(state.cross_contract_gas + 2 * CALLBACK_GAS.as_gas())
+}
+
+/// Callback for the relayer call made by [`bridge_transfer`]. A failed relay refunds the sender.
+/// Returns whether the relayer took the transfer.
+pub fn handle_bridge_relay(state: &mut FtWrapperContractState, transfer_id: u64) -> bool {
+    // Anything but an explicit acceptance counts as a failed relay
+    let relayed = match env::promise_result(0) {
+        PromiseResult::Successful(value) => match serde_json::from_slice::<RelayResponse>(&value) {
+            Ok(response) if response.accepted => true,
+            Ok(response) => {
+                env::log_str(&format!(
+                    "Relayer rejected bridge transfer {}: {}",
+                    transfer_id,
+                    response.reason.unwrap_or_default(),
+                ));
+                false
+            }
+            Err(_) => false,
+        },
+        _ => false,
+    };
+    // A timeout refund may already have moved the transfer on
+    match state.bridge_transfers.get(&transfer_id) {
+        Some(transfer) if transfer.status == BridgeTransferStatus::Pending => {}
+        _ => return false,
+    }
+    if relayed {
+        set_transfer_status(state, transfer_id, BridgeTransferStatus::Relayed);
+    } else {
+        set_transfer_status(state, transfer_id, BridgeTransferStatus::Failed);
+        FtWrapperEvent::BridgeTransferFailed { transfer_id }.emit();
+        start_bridge_refund(state, transfer_id);
+    }
+    relayed
+}
+
+/// Lets the sender take back the locked tokens of a failed transfer, or of a transfer the
+/// relayer has not completed within `bridge_refund_timeout_ms`.
+pub fn refund_bridge_transfer(state: &mut FtWrapperContractState, transfer_id: u64) -> Result<Promise, FtWrapperError> {
+    let transfer = state.bridge_transfers.get(&transfer_id).ok_or(FtWrapperError::TransferNotFound)?;
+    if transfer.sender != env::predecessor_account_id() {
+        return Err(FtWrapperError::Unauthorized);
+    }
+    match transfer.status {
+        BridgeTransferStatus::Failed => {}
+        BridgeTransferStatus::Pending | BridgeTransferStatus::Relayed => {
+            if env::block_timestamp_ms() < transfer.created_at + state.bridge_refund_timeout_ms {
+                return Err(FtWrapperError::RefundTimeoutNotReached);
+            }
+        }
+        _ => return Err(FtWrapperError::InvalidTransferStatus),
+    }
+    Ok(start_bridge_refund(state, transfer_id))
+}
+
+fn start_bridge_refund(state: &mut FtWrapperContractState, transfer_id: u64) -> Promise {
+    set_transfer_status(state, transfer_id, BridgeTransferStatus::Refunding);
+    let transfer = state.bridge_transfers.get(&transfer_id).expect("Bridge transfer not found");
+    ext_ft::ext(transfer.token.clone())
+        .with_static_gas(Gas::from_gas(state.cross_contract_gas))
+        .with_attached_deposit(NearToken::from_yoctonear(1))
+        .ft_transfer(transfer.sender.clone(), transfer.amount, Some(format!("Bridge transfer {} refund", transfer_id)))
+        .then(
+            ext_self::ext(env::current_account_id())
+                .with_static_gas(CALLBACK_GAS)
+                .handle_bridge_refund(transfer_id),
+        )
+}
+
+pub fn handle_bridge_refund(state: &mut FtWrapperContractState, transfer_id: u64) {
+    let refunded = matches!(env::promise_result(0), PromiseResult::Successful(_));
+    if !state.bridge_transfers.contains_key(&transfer_id) {
+        return;
+    }
+    if !refunded {
+        // Leave the transfer refundable so the sender can try again
+        set_transfer_status(state, transfer_id, BridgeTransferStatus::Failed);
+        env::log_str(&format!("Refund of bridge transfer {} failed", transfer_id));
+        return;
+    }
+    set_transfer_status(state, transfer_id, BridgeTransferStatus::Refunded);
+    let transfer = &state.bridge_transfers[&transfer_id];
+    let (token, sender, amount) = (transfer.token.clone(), transfer.sender.clone(), transfer.amount);
+    let locked = state.locked_balances.get(&token).copied().unwrap_or(0);
+    state.locked_balances.insert(token.clone(), locked.saturating_sub(amount.0));
+    FtWrapperEvent::BridgeTransferRefunded { transfer_id, token, sender, amount }.emit();
+}
+
+/// Called by the relayer once the transfer has been delivered on the destination chain.
+pub fn complete_bridge_transfer(state: &mut FtWrapperContractState, transfer_id: u64) -> Result<PromiseOrValue<()>, FtWrapperError> {
+    if env::predecessor_account_id() != state.relayer_contract {
+        return Err(FtWrapperError::Unauthorized);
+    }
+    let transfer = state.bridge_transfers.get(&transfer_id).ok_or(FtWrapperError::TransferNotFound)?;
+    if !matches!(transfer.status, BridgeTransferStatus::Pending | BridgeTransferStatus::Relayed) {
+        return Err(FtWrapperError::InvalidTransferStatus);
+    }
+    let (token, amount) = (transfer.token.clone(), transfer.amount);
+
+    // Native tokens stay locked: they now back the tokens delivered on the destination chain
+    // and are only unlocked when finalize_transfer releases them on the way back
+    if !crate::factory::is_bridged_token(state, &token) {
+        set_transfer_status(state, transfer_id, BridgeTransferStatus::Completed);
+        FtWrapperEvent::BridgeTransferCompleted { transfer_id }.emit();
+        return Ok(PromiseOrValue::Value(()));
+    }
+
+    // Bridged tokens stay locked while a refund is still possible and are burned once they
+    // exist on the destination chain; the transfer completes when the burn does. Completing
+    // keeps the sender from refunding tokens that are being burned
+    set_transfer_status(state, transfer_id, BridgeTransferStatus::Completing);
+    Ok(PromiseOrValue::Promise(crate::factory::burn(state, token, amount).then(
+        ext_self::ext(env::current_account_id())
+            .with_static_gas(CALLBACK_GAS)
+            .handle_bridge_burn(transfer_id),
+    )))
+}
+
+/// Callback for the burn started by [`complete_bridge_transfer`]. A failed burn moves the
+/// transfer back to `Relayed` with its lock untouched, so the relayer can report completion
+/// again.
+pub fn handle_bridge_burn(state: &mut FtWrapperContractState, transfer_id: u64) -> bool {
+    let transfer = match state.bridge_transfers.get(&transfer_id) {
+        Some(transfer) if transfer.status == BridgeTransferStatus::Completing => transfer,
+        _ => return false,
+    };
+    if !matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+        set_transfer_status(state, transfer_id, BridgeTransferStatus::Relayed);
+        env::log_str(&format!("Burn for bridge transfer {} failed", transfer_id));
+        return false;
+    }
+    let (token, amount) = (transfer.token.clone(), transfer.amount);
+    set_transfer_status(state, transfer_id, BridgeTransferStatus::Completed);
+    let locked = state.locked_balances.get(&token).copied().unwrap_or(0);
+    state.locked_balances.insert(token, locked.saturating_sub(amount.0));
+    FtWrapperEvent::BridgeTransferCompleted { transfer_id }.emit();
+    true
+}
+
+/// Tokens the wrapper holds for outbound transfers. For native tokens this includes completed
+/// transfers, whose tokens stay locked until they are bridged back.
+pub fn get_locked_balance(state: &FtWrapperContractState, token: AccountId) -> U128 {
+    U128(state.locked_balances.get(&token).copied().unwrap_or(0))
+}
+
+pub fn get_bridge_transfer(state: &FtWrapperContractState, transfer_id: u64) -> Option<BridgeTransfer> {
+    state.bridge_transfers.get(&transfer_id).cloned()
+}
+
+pub fn get_bridge_transfers_by_sender(
+    state: &FtWrapperContractState,
+    sender: AccountId,
+    from_index: Option<u64>,
+    limit: Option<u64>,
+) -> Vec<BridgeTransfer> {
+    let ids = match state.bridge_transfers_by_sender.get(&sender) {
+        Some(ids) => ids,
+        None => return Vec::new(),
+    };
+    ids.iter()
+        .skip(from_index.unwrap_or(0) as usize)
+        .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+        .filter_map(|id| state.bridge_transfers.get(id).cloned())
+        .collect()
+}
+
+pub fn get_bridge_transfers_by_status(
+    state: &FtWrapperContractState,
+    status: BridgeTransferStatus,
+    from_index: Option<u64>,
+    limit: Option<u64>,
+) -> Vec<BridgeTransfer> {
+    let ids = match state.bridge_transfers_by_status.get(&status) {
+        Some(ids) => ids,
+        None => return Vec::new(),
+    };
+    ids.iter()
+        .skip(from_index.unwrap_or(0) as usize)
+        .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+        .filter_map(|id| state.bridge_transfers.get(id).cloned())
+        .collect()
+}
+
+/// Moves a bridge transfer to `status` and keeps the per-status index in step.
+fn set_transfer_status(state: &mut FtWrapperContractState, transfer_id: u64, status: BridgeTransferStatus) {
+    let transfer = state.bridge_transfers.get_mut(&transfer_id).expect("Bridge transfer not found");
+    let previous = transfer.status;
+    transfer.status = status;
+    transfer.updated_at = env::block_timestamp_ms();
+    if let Some(ids) = state.bridge_transfers_by_status.get_mut(&previous) {
+        ids.remove(&transfer_id);
+    }
+    index_transfer_status(state, transfer_id, status);
+}
+
+fn index_transfer_status(state: &mut FtWrapperContractState, transfer_id: u64, status: BridgeTransferStatus) {
+    state.bridge_transfers_by_status
+        .entry(status)
+        .or_insert_with(|| IterableSet::new(vec![b'z', status as u8]))
+        .insert(transfer_id);
 }
 
 pub fn finalize_transfer(
@@ -343,61 +887,172 @@ pub fn finalize_transfer(
     args: FinalizeTransferArgs,
 ) -> Result<Promise, FtWrapperError> {
     state.assert_balance()?;
-    if !state.supported_tokens.contains(&args.token) {
+
+    // Verify the payload was signed by one of the registered MPC keys
+    if !verify_mpc_signature(&state.mpc_public_keys, &args.signature, &args.message_payload) {
+        return Err(FtWrapperError::InvalidSignature);
+    }
+
+    // Every value below comes from the signed message, never from the caller
+    let message = decode_bridge_message(&args)?;
+    if message.expires_at <= env::block_timestamp_ms() {
+        return Err(FtWrapperError::BridgeMessageExpired);
+    }
+    crate::chains::enabled_chain(state, &message.source_chain)?;
+    if !state.supported_tokens.contains(&message.token) {
         return Err(FtWrapperError::TokenNotSupported);
     }
-    if args.amount.0 == 0 {
+    if message.amount.0 == 0 {
         return Err(FtWrapperError::AmountTooLow);
     }
 
-    // Verify MPC signature (simplified; in practice, integrate with NEAR MPC or light client)
-    if !verify_mpc_signature(&args.signature, &args.message_payload) {
-        return Err(FtWrapperError::Unauthorized);
+    // Native tokens are released from the lock of earlier outbound transfers, never from the
+    // balances users deposited
+    let locked = state.locked_balances.get(&message.token).copied().unwrap_or(0);
+    if message.is_native && message.amount.0 > locked {
+        return Err(FtWrapperError::InsufficientLockedBalance);
+    }
+
+    // Consume the message so the same signed payload cannot be replayed
+    let message_id = message_id(&args.message_payload);
+    if !state.processed_messages.insert(message_id) {
+        return Err(FtWrapperError::MessageAlreadyProcessed);
     }
 
     // Calculate fees (based on fee_percentage or fixed amount)
-    let fee = (args.amount.0 as u128 * state.fee_percentage as u128) / 10000; // fee_percentage is in basis points
-    let net_amount = args.amount.0.checked_sub(fee).ok_or(FtWrapperError::AmountTooLow)?;
+    let fee = (message.amount.0 * state.fee_percentage as u128) / 10000; // fee_percentage is in basis points
+    let net_amount = message.amount.0.checked_sub(fee).ok_or(FtWrapperError::AmountTooLow)?;
 
     // Ensure recipient is registered
-    let recipient_promise = ensure_registered(state, args.token.clone(), args.recipient.clone())?;
+    let recipient_promise = ensure_registered(state, message.token.clone(), message.recipient.clone())?;
+
+    // The whole amount left the other chain, so none of it backs anything there any more. It is
+    // unlocked up front so that concurrent releases cannot spend the same lock
+    if message.is_native {
+        state.locked_balances.insert(message.token.clone(), locked - message.amount.0);
+    }
 
     // Handle token type: mint for bridged, release for native
-    let transfer_promise = if args.is_native {
+    let transfer_promise = if message.is_native {
         // Release native tokens from lock
-        ext_ft::ext(args.token.clone())
-            .with_static_gas(Gas::from_tgas(state.cross_contract_gas))
-            .ft_transfer(args.recipient.clone(), U128(net_amount), Some("Incoming bridge transfer".to_string()))
+        ext_ft::ext(message.token.clone())
+            .with_static_gas(Gas::from_gas(state.cross_contract_gas))
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .ft_transfer(message.recipient.clone(), U128(net_amount), Some("Incoming bridge transfer".to_string()))
     } else {
         // Mint bridged tokens
-        ext_ft::ext(args.token.clone())
-            .with_static_gas(Gas::from_tgas(state.cross_contract_gas))
-            .ft_transfer(args.recipient.clone(), U128(net_amount), Some("Mint bridged tokens".to_string()))
+        crate::factory::mint(state, &message.token, &message.source_chain, message.recipient.clone(), U128(net_amount))?
     };
 
-    // Transfer fees to relayer if applicable
-    let fee_promise = if fee > 0 {
-        Promise::new(state.relayer_contract.clone())
-            .transfer(NearToken::from_yoctonear(fee))
+    // The relayer's fee is paid in the token once the transfer has gone through; a bridged
+    // token's fee is minted from the callback, which needs the gas for it
+    let callback_gas = if fee > 0 && !message.is_native {
+        Gas::from_gas(state.cross_contract_gas + CALLBACK_GAS.as_gas())
     } else {
-        Promise::new(env::current_account_id())
+        CALLBACK_GAS
     };
 
-    // Emit event for finalization
-    FtWrapperEvent::TransferFinalized {
-        token: args.token.clone(),
-        recipient: args.recipient.clone(),
-        amount: U128(net_amount),
-        fee: U128(fee),
-        source_chain: args.source_chain.clone(),
-    }.emit();
+    Ok(recipient_promise.then(transfer_promise).then(
+        ext_self::ext(env::current_account_id())
+            .with_static_gas(callback_gas)
+            .handle_finalize_transfer(message_id, message, U128(fee)),
+    ))
+}
 
-    Ok(recipient_promise.and(fee_promise).then(transfer_promise))
+/// Callback for the release or mint of [`finalize_transfer`]. TransferFinalized is only emitted
+/// once the tokens reached the recipient; a failed transfer gives the message and any native
+/// lock back, so the same signed payload can be submitted again.
+pub fn handle_finalize_transfer(state: &mut FtWrapperContractState, message_id: [u8; 32], message: BridgeMessage, fee: U128) -> bool {
+    if matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+        let relayer = state.relayer_contract.clone();
+        if message.is_native {
+            // The fee share stays with the wrapper as the relayer's balance
+            if fee.0 > 0 {
+                credit(state, &message.token, &relayer, fee.0);
+            }
+        } else if fee.0 > 0 {
+            if let Err(error) = crate::factory::mint(state, &message.token, &message.source_chain, relayer, fee) {
+                env::log_str(&format!("Relayer fee not minted: {}", error));
+            }
+        }
+        FtWrapperEvent::TransferFinalized {
+            token: message.token,
+            recipient: message.recipient,
+            amount: U128(message.amount.0 - fee.0),
+            fee,
+            source_chain: message.source_chain,
+        }.emit();
+        return true;
+    }
+    state.processed_messages.remove(&message_id);
+    if message.is_native {
+        let locked = state.locked_balances.get(&message.token).copied().unwrap_or(0);
+        state.locked_balances.insert(message.token.clone(), locked + message.amount.0);
+    }
+    env::log_str(&format!("Bridge message {} was not delivered", crate::address::to_hex(&message_id)));
+    false
 }
 
-// Placeholder for MPC signature verification (to be implemented with NEAR MPC or light client)
-fn verify_mpc_signature(_signature: &[u8], _payload: &[u8]) -> bool {
-    // TODO: Integrate NEAR MPC verification or light client proof validation
-    // For now, return true for demonstration (replace with actual logic)
-    true
-}
\ No newline at end of file
+/// Decodes the signed payload as a [`BridgeMessage`] and checks that the plain arguments
+/// submitted next to it describe the same transfer.
+fn decode_bridge_message(args: &FinalizeTransferArgs) -> Result<BridgeMessage, FtWrapperError> {
+    let message: BridgeMessage = borsh::from_slice(&args.message_payload)
+        .map_err(|_| FtWrapperError::InvalidBridgeMessage)?;
+    if message.version != BRIDGE_MESSAGE_VERSION {
+        return Err(FtWrapperError::InvalidBridgeMessage);
+    }
+    if message.token != args.token
+        || message.recipient != args.recipient
+        || message.amount != args.amount
+        || message.is_native != args.is_native
+        || message.source_chain != args.source_chain
+    {
+        return Err(FtWrapperError::BridgeMessageMismatch);
+    }
+    Ok(message)
+}
+
+/// Identifies an inbound bridge message by the sha256 hash of its signed payload.
+pub fn message_id(payload: &[u8]) -> [u8; 32] {
+    env::sha256_array(payload)
+}
+
+pub fn is_message_processed(state: &FtWrapperContractState, message_payload: Vec<u8>) -> bool {
+    state.processed_messages.contains(&message_id(&message_payload))
+}
+
+/// Checks `signature` over `payload` against the registered MPC keys.
+///
+/// A 65-byte signature (`r || s || v`) is treated as secp256k1 over the keccak256 hash of the
+/// payload, with `v` either 0/1 or 27/28. A 64-byte signature is treated as ed25519 over the raw
+/// payload. Any other length is rejected.
+fn verify_mpc_signature(mpc_public_keys: &[PublicKey], signature: &[u8], payload: &[u8]) -> bool {
+    match signature.len() {
+        65 => {
+            let hash = env::keccak256_array(payload);
+            let v = match signature[64] {
+                v @ 0..=1 => v,
+                v @ 27..=28 => v - 27,
+                _ => return false,
+            };
+            let recovered = match env::ecrecover(&hash, &signature[..64], v, true) {
+                Some(key) => key,
+                None => return false,
+            };
+            mpc_public_keys.iter().any(|key| {
+                key.curve_type() == CurveType::SECP256K1 && key.as_bytes()[1..] == recovered[..]
+            })
+        }
+        64 => {
+            let signature: &[u8; 64] = signature.try_into().unwrap();
+            mpc_public_keys.iter().any(|key| {
+                if key.curve_type() != CurveType::ED25519 {
+                    return false;
+                }
+                let key_bytes: &[u8; 32] = key.as_bytes()[1..].try_into().unwrap();
+                env::ed25519_verify(signature, payload, key_bytes)
+            })
+        }
+        _ => false,
+    }
+}
@@ -1,6 +1,6 @@
-use near_sdk::{near, env, AccountId, Promise, ext_contract, PanicOnDefault, NearToken, Gas};
+use near_sdk::{near, env, AccountId, PublicKey, Promise, PromiseOrValue, ext_contract, PanicOnDefault, NearToken, Gas};
 use near_sdk::json_types::U128;
-use crate::types::{FtTransferArgs, RequestChainSignatureArgs, BridgeTransferArgs, StorageBalance, StorageBalanceBounds, FinalizeTransferArgs}; // Added FinalizeTransferArgs
+use crate::types::{FtTransferArgs, BridgeTransferArgs, RequestChainSignatureArgs, StorageBalance, StorageBalanceBounds, FinalizeTransferArgs, BridgeMessage, BridgeTransfer, BridgeTransferStatus, BridgedAsset, BridgedTokenMetadata, ChainConfig, SignRequest, SignatureRequest, SignatureResponse, BitcoinSpend, Action, ActionOutcome, IntentKey, SignedDelegateAction, RegistrationStatus, StorageSponsorship, RelayOperation, RelayResponse}; // Added FinalizeTransferArgs
 use crate::state::FtWrapperContractState;
 use crate::errors::FtWrapperError;
 use crate::events::FtWrapperEvent;
@@ -11,22 +11,59 @@ mod events;
 mod state;
 mod admin;
 mod ft;
+mod factory;
+mod chains;
+mod address;
+mod signer;
+mod evm;
+mod bitcoin;
+mod actions;
+mod delegate;
 mod state_versions;
 
 #[ext_contract(ext_ft)]
 pub trait FungibleToken {
     fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+    fn ft_transfer_call(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>, msg: String) -> PromiseOrValue<U128>;
     fn storage_deposit(&mut self, account_id: Option<AccountId>, registration_only: Option<bool>) -> StorageBalance;
     fn ft_balance_of(&self, account_id: AccountId) -> U128;
     fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance>;
     fn storage_balance_bounds(&self) -> StorageBalanceBounds;
 }
 
+#[ext_contract(ext_bridged_token)]
+pub trait BridgedToken {
+    fn mint(&mut self, account_id: AccountId, amount: U128);
+    fn burn(&mut self, account_id: AccountId, amount: U128);
+}
+
+#[ext_contract(ext_relayer)]
+pub trait Relayer {
+    fn relay_meta_transaction(&mut self, operation: RelayOperation, sender_id: AccountId, actions: Vec<Action>) -> RelayResponse;
+}
+
+#[ext_contract(ext_mpc_signer)]
+pub trait MpcSigner {
+    fn sign(&mut self, request: SignRequest) -> SignatureResponse;
+}
+
 #[ext_contract(ext_self)]
 pub trait SelfCallback {
     fn handle_registration(&mut self, token: AccountId, account_id: AccountId) -> Promise;
-    fn handle_storage_deposit(&mut self, token: AccountId, account_id: AccountId) -> Promise;
+    fn handle_storage_deposit(&mut self, token: AccountId, account_id: AccountId, amount: U128, payer: AccountId, balance: StorageBalance) -> bool;
     fn handle_balance_check(&mut self, token: AccountId, account_id: AccountId) -> bool;
+    fn handle_reconciliation(&mut self, token: AccountId, accounts: Vec<AccountId>);
+    fn handle_ft_transfer(&mut self, token: AccountId, sender_id: AccountId, receiver_id: AccountId, amount: U128) -> bool;
+    fn handle_ft_transfer_call(&mut self, token: AccountId, sender_id: AccountId, receiver_id: AccountId, amount: U128) -> U128;
+    fn handle_withdraw(&mut self, token: AccountId, account_id: AccountId, receiver_id: AccountId, amount: U128) -> bool;
+    fn handle_finalize_transfer(&mut self, message_id: [u8; 32], message: BridgeMessage, fee: U128) -> bool;
+    fn handle_bridge_relay(&mut self, transfer_id: u64) -> bool;
+    fn handle_bridge_refund(&mut self, transfer_id: u64);
+    fn handle_bridge_burn(&mut self, transfer_id: u64) -> bool;
+    fn handle_bridged_token_deployed(&mut self, source_chain: String, address: String, token: AccountId) -> bool;
+    fn handle_signature_result(&mut self, request_id: u64, attempt: u32) -> Option<SignatureRequest>;
+    fn handle_bitcoin_spend(&self, spend_id: u64) -> Option<BitcoinSpend>;
+    fn handle_action_result(&mut self, batch_id: u64, index: u32) -> PromiseOrValue<Vec<ActionOutcome>>;
 }
 
 #[near(contract_state)]
@@ -50,12 +87,12 @@ impl FtWrapperContract {
         use state_versions::{StateV010, StateV011};
         use near_sdk::borsh;
 
-        const CURRENT_VERSION: &str = "0.1.1";
+        const CURRENT_VERSION: &str = "0.2.0";
 
         // Read raw state bytes, default to empty if none
         let state_bytes: Vec<u8> = env::state_read().unwrap_or_default();
 
-        // Try current version (0.1.1)
+        // Try current version (0.2.0)
         if let Ok(state) = borsh::from_slice::<FtWrapperContractState>(&state_bytes) {
             if state.version == CURRENT_VERSION {
                 env::log_str("State is already at latest version");
@@ -67,18 +104,18 @@ impl FtWrapperContract {
         if let Ok(old_state) = borsh::from_slice::<StateV011>(&state_bytes) {
             if old_state.version == "0.1.1" {
                 env::log_str("Migrating from state version 0.1.1");
-                let new_state = FtWrapperContractState {
-                    version: CURRENT_VERSION.to_string(),
-                    manager: old_state.manager,
-                    relayer_contract: old_state.relayer_contract,
-                    supported_tokens: old_state.supported_tokens,
-                    storage_deposit: old_state.storage_deposit,
-                    cross_contract_gas: old_state.cross_contract_gas,
-                    storage_balances: old_state.storage_balances,
-                    min_balance: old_state.min_balance,
-                    max_balance: old_state.max_balance,
-                    fee_percentage: old_state.fee_percentage,
-                };
+                // Fields introduced after 0.1.1 keep the defaults set by `new`
+                let mut new_state = FtWrapperContractState::new(
+                    old_state.manager,
+                    old_state.relayer_contract,
+                    old_state.storage_deposit,
+                );
+                new_state.supported_tokens = old_state.supported_tokens;
+                new_state.cross_contract_gas = old_state.cross_contract_gas;
+                new_state.storage_balances = old_state.storage_balances;
+                new_state.min_balance = old_state.min_balance;
+                new_state.max_balance = old_state.max_balance;
+                new_state.fee_percentage = old_state.fee_percentage;
                 FtWrapperEvent::StateMigrated {
                     old_version: "0.1.1".to_string(),
                     new_version: CURRENT_VERSION.to_string(),
@@ -91,18 +128,16 @@ impl FtWrapperContract {
         if let Ok(old_state) = borsh::from_slice::<StateV010>(&state_bytes) {
             if old_state.version == "0.1.0" {
                 env::log_str("Migrating from state version 0.1.0");
-                let new_state = FtWrapperContractState {
-                    version: CURRENT_VERSION.to_string(),
-                    manager: old_state.manager,
-                    relayer_contract: old_state.relayer_contract,
-                    supported_tokens: old_state.supported_tokens,
-                    storage_deposit: old_state.storage_deposit,
-                    cross_contract_gas: old_state.cross_contract_gas,
-                    storage_balances: old_state.storage_balances,
-                    min_balance: old_state.min_balance,
-                    max_balance: old_state.max_balance,
-                    fee_percentage: 0,
-                };
+                let mut new_state = FtWrapperContractState::new(
+                    old_state.manager,
+                    old_state.relayer_contract,
+                    old_state.storage_deposit,
+                );
+                new_state.supported_tokens = old_state.supported_tokens;
+                new_state.cross_contract_gas = old_state.cross_contract_gas;
+                new_state.storage_balances = old_state.storage_balances;
+                new_state.min_balance = old_state.min_balance;
+                new_state.max_balance = old_state.max_balance;
                 FtWrapperEvent::StateMigrated {
                     old_version: "0.1.0".to_string(),
                     new_version: CURRENT_VERSION.to_string(),
@@ -137,28 +172,119 @@ impl FtWrapperContract {
         Ok(())
     }
 
+    /// Sends `args.amount` of the caller's deposited tokens to `args.receiver_id`. Requires
+    /// exactly one yoctoNEAR attached, as NEP-141 does.
+    #[payable]
     pub fn ft_transfer(&mut self, args: FtTransferArgs) -> Promise {
+        near_sdk::assert_one_yocto();
         self.ft_transfer_internal(args).expect("FT transfer failed")
     }
 
-    pub fn request_chain_signature(&mut self, args: RequestChainSignatureArgs) -> Promise {
-        self.request_chain_signature_internal(args).expect("Chain signature request failed")
+    /// Sends `amount` of the caller's deposited tokens to `receiver_id` with the token's
+    /// `ft_transfer_call`. Whatever the receiver does not use is credited back to the caller.
+    /// Requires exactly one yoctoNEAR attached.
+    #[payable]
+    pub fn ft_transfer_call(&mut self, token: AccountId, receiver_id: AccountId, amount: U128, memo: Option<String>, msg: String) -> Promise {
+        near_sdk::assert_one_yocto();
+        self.ft_transfer_call_internal(token, receiver_id, amount, memo, msg).expect("FT transfer call failed")
     }
 
+    /// Sends `amount` of the caller's deposited tokens to `receiver_id`, or back to the caller.
+    /// Requires exactly one yoctoNEAR attached.
+    #[payable]
+    pub fn withdraw(&mut self, token: AccountId, amount: U128, receiver_id: Option<AccountId>) -> Promise {
+        near_sdk::assert_one_yocto();
+        self.withdraw_internal(token, amount, receiver_id).expect("Withdraw failed")
+    }
+
+    /// Bridges `args.amount` of the caller's deposited tokens to `args.recipient` on
+    /// `args.destination_chain`. Requires exactly one yoctoNEAR attached.
+    #[payable]
     pub fn bridge_transfer(&mut self, args: BridgeTransferArgs) -> Promise {
+        near_sdk::assert_one_yocto();
         self.bridge_transfer_internal(args).expect("Bridge transfer failed")
     }
 
+    /// Signs `args.payload` with the MPC key derived for the caller and `args.derivation_path`.
+    /// The attached deposit is forwarded to the MPC signer as its fee.
+    #[payable]
+    pub fn request_chain_signature(&mut self, args: RequestChainSignatureArgs) -> Promise {
+        self.request_chain_signature_internal(args).expect("Chain signature request failed")
+    }
+
+    /// Resubmits a failed signature request. A new signer fee must be attached.
+    #[payable]
+    pub fn retry_signature_request(&mut self, request_id: u64) -> Promise {
+        self.retry_signature_request_internal(request_id).expect("Signature request retry failed")
+    }
+
+    /// Runs `actions` in order, each once the previous one has succeeded, and returns one outcome
+    /// per action. Account-level actions (FunctionCall, Transfer, AddKey) are manager-only;
+    /// FtTransfer and BridgeTransfer need at least one yoctoNEAR attached. The attached gas
+    /// must cover every action up front.
+    #[payable]
+    pub fn execute_actions(&mut self, actions: Vec<Action>) -> PromiseOrValue<Vec<ActionOutcome>> {
+        self.execute_actions_internal(actions).expect("Execute actions failed")
+    }
+
+    /// Ends one of the caller's batches that has been stuck on an action for an hour and
+    /// refunds its unused deposit.
+    #[handle_result]
+    pub fn cancel_action_batch(&mut self, batch_id: u64) -> Result<Vec<ActionOutcome>, FtWrapperError> {
+        self.cancel_action_batch_internal(batch_id)
+    }
+
+    /// Registers an ed25519 key the caller signs delegate actions with. `expires_at` is a block
+    /// timestamp in milliseconds; `methods` limits the key to `ft_transfer` and/or `bridge_transfer`.
+    /// An account holds at most ten unexpired keys.
+    #[handle_result]
+    pub fn register_intent_key(&mut self, public_key: PublicKey, expires_at: Option<u64>, methods: Option<Vec<String>>) -> Result<(), FtWrapperError> {
+        self.register_intent_key_internal(public_key, expires_at, methods)
+    }
+
+    #[handle_result]
+    pub fn revoke_intent_key(&mut self, public_key: PublicKey) -> Result<(), FtWrapperError> {
+        self.revoke_intent_key_internal(public_key)
+    }
+
+    /// Runs the `FtTransfer` and `BridgeTransfer` actions of a NEP-366 style delegate action as
+    /// its signer, so a relayer can submit them on the signer's behalf.
+    pub fn submit_signed_delegate(&mut self, signed_delegate: SignedDelegateAction) -> PromiseOrValue<Vec<ActionOutcome>> {
+        self.submit_signed_delegate_internal(signed_delegate).expect("Signed delegate failed")
+    }
+
+    /// NEP-141 receiver. Tokens sent with `ft_transfer_call` and an empty `msg` are credited to
+    /// the sender's balance; with a JSON `msg` of `{"destination_chain": .., "recipient": ..}`
+    /// they are locked and bridged out.
+    pub fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
+        self.ft_on_transfer_internal(sender_id, amount, msg).expect("Transfer handling failed")
+    }
+
     pub fn finalize_transfer(&mut self, args: FinalizeTransferArgs) -> Promise {
         self.finalize_transfer_internal(args).expect("Finalize transfer failed")
     }
 
+    pub fn refund_bridge_transfer(&mut self, transfer_id: u64) -> Promise {
+        self.refund_bridge_transfer_internal(transfer_id).expect("Bridge refund failed")
+    }
+
+    #[handle_result]
+    pub fn complete_bridge_transfer(&mut self, transfer_id: u64) -> Result<PromiseOrValue<()>, FtWrapperError> {
+        self.complete_bridge_transfer_internal(transfer_id)
+    }
+
+    /// NEP-145 registration of `account_id` on `token`. The attached deposit must cover the
+    /// token's minimum; without one the wrapper pays if storage sponsorship is enabled.
+    #[payable]
     pub fn storage_deposit(&mut self, token: AccountId, account_id: Option<AccountId>, registration_only: Option<bool>) -> StorageBalance {
         self.storage_deposit_internal(token, account_id, registration_only).expect("Storage deposit failed")
     }
 
+    /// Withdraws up to `amount` of the caller's available storage balance on `token`, or all
+    /// of it. Requires exactly one yoctoNEAR attached, as NEP-145 does.
     #[payable]
     pub fn storage_withdraw(&mut self, token: AccountId, amount: Option<U128>) -> StorageBalance {
+        near_sdk::assert_one_yocto();
         self.storage_withdraw_internal(token, amount).expect("Storage withdraw failed")
     }
 
@@ -166,12 +292,21 @@ impl FtWrapperContract {
         self.storage_balance_of_internal(token, account_id)
     }
 
+    /// Checks the token's storage registration of up to 10 `accounts` and fixes local records
+    /// that disagree with it. Manager-only.
+    #[handle_result]
+    pub fn reconcile_registrations(&mut self, token: AccountId, accounts: Vec<AccountId>) -> Result<Promise, FtWrapperError> {
+        self.reconcile_registrations_internal(token, accounts)
+    }
+
     pub fn storage_balance_bounds(&self, token: AccountId) -> Promise {
         self.storage_balance_bounds_internal(token)
     }
 
+    /// Requires exactly one yoctoNEAR attached, as NEP-145 does.
     #[payable]
     pub fn storage_unregister(&mut self, token: AccountId, force: Option<bool>) -> bool {
+        near_sdk::assert_one_yocto();
         self.storage_unregister_internal(token, force).expect("Storage unregister failed")
     }
 
@@ -195,6 +330,12 @@ impl FtWrapperContract {
         self.set_storage_deposit_internal(storage_deposit)
     }
 
+    /// Chooses whether the wrapper pays storage deposits nobody attached. Manager-only.
+    #[handle_result]
+    pub fn set_storage_sponsorship(&mut self, policy: StorageSponsorship) -> Result<(), FtWrapperError> {
+        self.set_storage_sponsorship_internal(policy)
+    }
+
     #[handle_result]
     pub fn set_manager(&mut self, new_manager: AccountId) -> Result<(), FtWrapperError> {
         self.state.set_manager(new_manager.clone())?;
@@ -202,8 +343,155 @@ impl FtWrapperContract {
         Ok(())
     }
 
+    #[handle_result]
+    pub fn add_chain(&mut self, config: ChainConfig) -> Result<(), FtWrapperError> {
+        self.add_chain_internal(config)
+    }
+
+    #[handle_result]
+    pub fn update_chain(&mut self, config: ChainConfig) -> Result<(), FtWrapperError> {
+        self.update_chain_internal(config)
+    }
+
+    #[handle_result]
+    pub fn set_chain_enabled(&mut self, chain_id: String, enabled: bool) -> Result<(), FtWrapperError> {
+        self.set_chain_enabled_internal(chain_id, enabled)
+    }
+
+    #[handle_result]
+    pub fn remove_chain(&mut self, chain_id: String) -> Result<(), FtWrapperError> {
+        self.remove_chain_internal(chain_id)
+    }
+
+    pub fn get_chain(&self, chain_id: String) -> Option<ChainConfig> {
+        crate::chains::get_chain(&self.state, chain_id)
+    }
+
+    pub fn get_chains(&self) -> Vec<ChainConfig> {
+        crate::chains::get_chains(&self.state)
+    }
+
+    #[handle_result]
+    pub fn set_bridged_token_code(&mut self) -> Result<(), FtWrapperError> {
+        let code = env::input().ok_or(FtWrapperError::BridgedTokenCodeMissing)?.to_vec();
+        self.set_bridged_token_code_internal(code)
+    }
+
+    #[payable]
+    #[handle_result]
+    pub fn deploy_bridged_token(&mut self, source_chain: String, address: String, prefix: String, metadata: BridgedTokenMetadata) -> Result<Promise, FtWrapperError> {
+        self.deploy_bridged_token_internal(source_chain, address, prefix, metadata)
+    }
+
+    pub fn get_bridged_token(&self, source_chain: String, address: String) -> Option<AccountId> {
+        crate::factory::get_bridged_token(&self.state, source_chain, address)
+    }
+
+    pub fn get_bridged_token_origin(&self, token: AccountId) -> Option<BridgedAsset> {
+        crate::factory::get_bridged_token_origin(&self.state, token)
+    }
+
+    #[handle_result]
+    pub fn set_bridge_refund_timeout(&mut self, timeout_ms: u64) -> Result<(), FtWrapperError> {
+        self.set_bridge_refund_timeout_internal(timeout_ms)
+    }
+
+    #[handle_result]
+    pub fn add_mpc_public_key(&mut self, public_key: PublicKey) -> Result<(), FtWrapperError> {
+        self.add_mpc_public_key_internal(public_key)
+    }
+
+    #[handle_result]
+    pub fn rotate_mpc_public_key(&mut self, old_public_key: PublicKey, new_public_key: PublicKey) -> Result<(), FtWrapperError> {
+        self.rotate_mpc_public_key_internal(old_public_key, new_public_key)
+    }
+
+    #[handle_result]
+    pub fn retire_mpc_public_key(&mut self, public_key: PublicKey) -> Result<(), FtWrapperError> {
+        self.retire_mpc_public_key_internal(public_key)
+    }
+
+    #[handle_result]
+    pub fn set_mpc_signer(&mut self, signer_contract: AccountId, key_version: u32) -> Result<(), FtWrapperError> {
+        self.set_mpc_signer_internal(signer_contract, key_version)
+    }
+
+    #[handle_result]
+    pub fn set_signature_request_timeout(&mut self, timeout_ms: u64) -> Result<(), FtWrapperError> {
+        self.set_signature_request_timeout_internal(timeout_ms)
+    }
+
+    #[handle_result]
+    pub fn set_mpc_root_public_key(&mut self, public_key: PublicKey) -> Result<(), FtWrapperError> {
+        self.set_mpc_root_public_key_internal(public_key)
+    }
+
+    /// Address on `chain` controlled by the MPC key `account_id` gets for `derivation_path`.
+    /// Fails for ed25519 chains, which the wrapper cannot sign for yet.
+    #[handle_result]
+    pub fn derived_address(&self, account_id: AccountId, derivation_path: String, chain: String) -> Result<String, FtWrapperError> {
+        self.derived_address_internal(account_id, derivation_path, chain)
+    }
+
+    pub fn get_signature_request(&self, request_id: u64) -> Option<SignatureRequest> {
+        crate::signer::get_signature_request(&self.state, request_id)
+    }
+
+    pub fn get_bitcoin_spend(&self, spend_id: u64) -> Option<BitcoinSpend> {
+        crate::signer::get_bitcoin_spend(&self.state, spend_id)
+    }
+
+    pub fn get_signature_requests_by_account(&self, account_id: AccountId, from_index: Option<u64>, limit: Option<u64>) -> Vec<SignatureRequest> {
+        crate::signer::get_signature_requests_by_account(&self.state, account_id, from_index, limit)
+    }
+
+    pub fn get_intent_keys(&self, account_id: AccountId) -> Vec<IntentKey> {
+        crate::delegate::get_intent_keys(&self.state, account_id)
+    }
+
+    pub fn get_intent_nonce(&self, account_id: AccountId) -> u64 {
+        crate::delegate::get_intent_nonce(&self.state, account_id)
+    }
+
+    pub fn get_mpc_public_keys(&self) -> Vec<PublicKey> {
+        self.state.mpc_public_keys.clone()
+    }
+
+    pub fn is_message_processed(&self, message_payload: Vec<u8>) -> bool {
+        crate::ft::is_message_processed(&self.state, message_payload)
+    }
+
+    pub fn get_storage_sponsorship(&self) -> StorageSponsorship {
+        self.state.storage_sponsorship
+    }
+
+    pub fn get_registration_status(&self, token: AccountId, account_id: AccountId) -> Option<RegistrationStatus> {
+        crate::ft::get_registration_status(&self.state, token, account_id)
+    }
+
+    /// Tokens `account_id` deposited with the wrapper and can transfer or bridge.
+    pub fn get_balance(&self, token: AccountId, account_id: AccountId) -> U128 {
+        crate::ft::get_balance(&self.state, token, account_id)
+    }
+
+    pub fn get_locked_balance(&self, token: AccountId) -> U128 {
+        crate::ft::get_locked_balance(&self.state, token)
+    }
+
+    pub fn get_bridge_transfer(&self, transfer_id: u64) -> Option<BridgeTransfer> {
+        crate::ft::get_bridge_transfer(&self.state, transfer_id)
+    }
+
+    pub fn get_bridge_transfers_by_sender(&self, sender: AccountId, from_index: Option<u64>, limit: Option<u64>) -> Vec<BridgeTransfer> {
+        crate::ft::get_bridge_transfers_by_sender(&self.state, sender, from_index, limit)
+    }
+
+    pub fn get_bridge_transfers_by_status(&self, status: BridgeTransferStatus, from_index: Option<u64>, limit: Option<u64>) -> Vec<BridgeTransfer> {
+        crate::ft::get_bridge_transfers_by_status(&self.state, status, from_index, limit)
+    }
+
     pub fn get_supported_tokens(&self) -> Vec<AccountId> {
-        self.state.supported_tokens.iter().map(|token| token.clone()).collect()
+        self.state.supported_tokens.to_vec()
     }
 
     pub fn ft_balance_of(&mut self, token: AccountId, account_id: AccountId) -> Promise {
@@ -237,8 +525,68 @@ impl FtWrapperContract {
     }
 
     #[private]
-    pub fn handle_storage_deposit(&mut self, token: AccountId, account_id: AccountId) -> Promise {
-        self.handle_storage_deposit_internal(token, account_id)
+    pub fn handle_storage_deposit(&mut self, token: AccountId, account_id: AccountId, amount: U128, payer: AccountId, balance: StorageBalance) -> bool {
+        self.handle_storage_deposit_internal(token, account_id, amount, payer, balance)
+    }
+
+    #[private]
+    pub fn handle_finalize_transfer(&mut self, message_id: [u8; 32], message: BridgeMessage, fee: U128) -> bool {
+        crate::ft::handle_finalize_transfer(&mut self.state, message_id, message, fee)
+    }
+
+    #[private]
+    pub fn handle_bridge_relay(&mut self, transfer_id: u64) -> bool {
+        crate::ft::handle_bridge_relay(&mut self.state, transfer_id)
+    }
+
+    #[private]
+    pub fn handle_bridge_refund(&mut self, transfer_id: u64) {
+        crate::ft::handle_bridge_refund(&mut self.state, transfer_id)
+    }
+
+    #[private]
+    pub fn handle_bridge_burn(&mut self, transfer_id: u64) -> bool {
+        crate::ft::handle_bridge_burn(&mut self.state, transfer_id)
+    }
+
+    #[private]
+    pub fn handle_bridged_token_deployed(&mut self, source_chain: String, address: String, token: AccountId) -> bool {
+        crate::factory::handle_bridged_token_deployed(&mut self.state, source_chain, address, token)
+    }
+
+    #[private]
+    pub fn handle_signature_result(&mut self, request_id: u64, attempt: u32) -> Option<SignatureRequest> {
+        crate::signer::handle_signature_result(&mut self.state, request_id, attempt)
+    }
+
+    #[private]
+    pub fn handle_bitcoin_spend(&self, spend_id: u64) -> Option<BitcoinSpend> {
+        crate::signer::handle_bitcoin_spend(&self.state, spend_id)
+    }
+
+    #[private]
+    pub fn handle_action_result(&mut self, batch_id: u64, index: u32) -> PromiseOrValue<Vec<ActionOutcome>> {
+        crate::actions::handle_action_result(&mut self.state, batch_id, index)
+    }
+
+    #[private]
+    pub fn handle_ft_transfer(&mut self, token: AccountId, sender_id: AccountId, receiver_id: AccountId, amount: U128) -> bool {
+        crate::ft::handle_ft_transfer(&mut self.state, token, sender_id, receiver_id, amount)
+    }
+
+    #[private]
+    pub fn handle_ft_transfer_call(&mut self, token: AccountId, sender_id: AccountId, receiver_id: AccountId, amount: U128) -> U128 {
+        crate::ft::handle_ft_transfer_call(&mut self.state, token, sender_id, receiver_id, amount)
+    }
+
+    #[private]
+    pub fn handle_withdraw(&mut self, token: AccountId, account_id: AccountId, receiver_id: AccountId, amount: U128) -> bool {
+        crate::ft::handle_withdraw(&mut self.state, token, account_id, receiver_id, amount)
+    }
+
+    #[private]
+    pub fn handle_reconciliation(&mut self, token: AccountId, accounts: Vec<AccountId>) {
+        crate::ft::handle_reconciliation(&mut self.state, token, accounts)
     }
 
     #[private]
@@ -247,21 +595,65 @@ impl FtWrapperContract {
     }
 
     fn ft_transfer_internal(&mut self, args: FtTransferArgs) -> Result<Promise, FtWrapperError> {
-        crate::ft::ft_transfer(&mut self.state, args)
+        crate::ft::ft_transfer(&mut self.state, env::predecessor_account_id(), args)
     }
 
-    fn request_chain_signature_internal(&mut self, args: RequestChainSignatureArgs) -> Result<Promise, FtWrapperError> {
-        crate::ft::request_chain_signature(&mut self.state, args)
+    fn ft_transfer_call_internal(&mut self, token: AccountId, receiver_id: AccountId, amount: U128, memo: Option<String>, msg: String) -> Result<Promise, FtWrapperError> {
+        crate::ft::ft_transfer_call(&mut self.state, env::predecessor_account_id(), token, receiver_id, amount, memo, msg)
+    }
+
+    fn withdraw_internal(&mut self, token: AccountId, amount: U128, receiver_id: Option<AccountId>) -> Result<Promise, FtWrapperError> {
+        crate::ft::withdraw(&mut self.state, token, amount, receiver_id)
     }
 
     fn bridge_transfer_internal(&mut self, args: BridgeTransferArgs) -> Result<Promise, FtWrapperError> {
-        crate::ft::bridge_transfer(&mut self.state, args)
+        crate::ft::bridge_from_balance(&mut self.state, env::predecessor_account_id(), args)
+    }
+
+    fn request_chain_signature_internal(&mut self, args: RequestChainSignatureArgs) -> Result<Promise, FtWrapperError> {
+        crate::signer::request_chain_signature(&mut self.state, args)
+    }
+
+    fn retry_signature_request_internal(&mut self, request_id: u64) -> Result<Promise, FtWrapperError> {
+        crate::signer::retry_signature_request(&mut self.state, request_id)
+    }
+
+    fn execute_actions_internal(&mut self, actions: Vec<Action>) -> Result<PromiseOrValue<Vec<ActionOutcome>>, FtWrapperError> {
+        crate::actions::execute_actions(&mut self.state, actions)
+    }
+
+    fn cancel_action_batch_internal(&mut self, batch_id: u64) -> Result<Vec<ActionOutcome>, FtWrapperError> {
+        crate::actions::cancel_action_batch(&mut self.state, batch_id)
+    }
+
+    fn register_intent_key_internal(&mut self, public_key: PublicKey, expires_at: Option<u64>, methods: Option<Vec<String>>) -> Result<(), FtWrapperError> {
+        crate::delegate::register_intent_key(&mut self.state, public_key, expires_at, methods.unwrap_or_default())
+    }
+
+    fn revoke_intent_key_internal(&mut self, public_key: PublicKey) -> Result<(), FtWrapperError> {
+        crate::delegate::revoke_intent_key(&mut self.state, public_key)
+    }
+
+    fn submit_signed_delegate_internal(&mut self, signed_delegate: SignedDelegateAction) -> Result<PromiseOrValue<Vec<ActionOutcome>>, FtWrapperError> {
+        crate::delegate::submit_signed_delegate(&mut self.state, signed_delegate)
+    }
+
+    fn ft_on_transfer_internal(&mut self, sender_id: AccountId, amount: U128, msg: String) -> Result<PromiseOrValue<U128>, FtWrapperError> {
+        crate::ft::ft_on_transfer(&mut self.state, sender_id, amount, msg)
     }
 
     fn finalize_transfer_internal(&mut self, args: FinalizeTransferArgs) -> Result<Promise, FtWrapperError> {
         crate::ft::finalize_transfer(&mut self.state, args)
     }
 
+    fn refund_bridge_transfer_internal(&mut self, transfer_id: u64) -> Result<Promise, FtWrapperError> {
+        crate::ft::refund_bridge_transfer(&mut self.state, transfer_id)
+    }
+
+    fn complete_bridge_transfer_internal(&mut self, transfer_id: u64) -> Result<PromiseOrValue<()>, FtWrapperError> {
+        crate::ft::complete_bridge_transfer(&mut self.state, transfer_id)
+    }
+
     fn storage_deposit_internal(&mut self, token: AccountId, account_id: Option<AccountId>, registration_only: Option<bool>) -> Result<StorageBalance, FtWrapperError> {
         crate::ft::storage_deposit(&mut self.state, token, account_id, registration_only)
     }
@@ -274,6 +666,10 @@ impl FtWrapperContract {
         crate::ft::storage_balance_of(&self.state, token, account_id)
     }
 
+    fn reconcile_registrations_internal(&mut self, token: AccountId, accounts: Vec<AccountId>) -> Result<Promise, FtWrapperError> {
+        crate::ft::reconcile_registrations(&mut self.state, token, accounts)
+    }
+
     fn storage_balance_bounds_internal(&self, token: AccountId) -> Promise {
         crate::ft::storage_balance_bounds(&self.state, token)
     }
@@ -298,16 +694,76 @@ impl FtWrapperContract {
         crate::admin::set_storage_deposit(&mut self.state, storage_deposit)
     }
 
+    fn set_storage_sponsorship_internal(&mut self, policy: StorageSponsorship) -> Result<(), FtWrapperError> {
+        crate::admin::set_storage_sponsorship(&mut self.state, policy)
+    }
+
+    fn add_chain_internal(&mut self, config: ChainConfig) -> Result<(), FtWrapperError> {
+        crate::chains::add_chain(&mut self.state, config)
+    }
+
+    fn update_chain_internal(&mut self, config: ChainConfig) -> Result<(), FtWrapperError> {
+        crate::chains::update_chain(&mut self.state, config)
+    }
+
+    fn set_chain_enabled_internal(&mut self, chain_id: String, enabled: bool) -> Result<(), FtWrapperError> {
+        crate::chains::set_chain_enabled(&mut self.state, chain_id, enabled)
+    }
+
+    fn remove_chain_internal(&mut self, chain_id: String) -> Result<(), FtWrapperError> {
+        crate::chains::remove_chain(&mut self.state, chain_id)
+    }
+
+    fn set_bridged_token_code_internal(&mut self, code: Vec<u8>) -> Result<(), FtWrapperError> {
+        crate::factory::set_bridged_token_code(&mut self.state, code)
+    }
+
+    fn deploy_bridged_token_internal(&mut self, source_chain: String, address: String, prefix: String, metadata: BridgedTokenMetadata) -> Result<Promise, FtWrapperError> {
+        crate::factory::deploy_bridged_token(&mut self.state, source_chain, address, prefix, metadata)
+    }
+
+    fn set_bridge_refund_timeout_internal(&mut self, timeout_ms: u64) -> Result<(), FtWrapperError> {
+        crate::admin::set_bridge_refund_timeout(&mut self.state, timeout_ms)
+    }
+
+    fn add_mpc_public_key_internal(&mut self, public_key: PublicKey) -> Result<(), FtWrapperError> {
+        crate::admin::add_mpc_public_key(&mut self.state, public_key)
+    }
+
+    fn rotate_mpc_public_key_internal(&mut self, old_public_key: PublicKey, new_public_key: PublicKey) -> Result<(), FtWrapperError> {
+        crate::admin::rotate_mpc_public_key(&mut self.state, old_public_key, new_public_key)
+    }
+
+    fn retire_mpc_public_key_internal(&mut self, public_key: PublicKey) -> Result<(), FtWrapperError> {
+        crate::admin::retire_mpc_public_key(&mut self.state, public_key)
+    }
+
+    fn set_mpc_signer_internal(&mut self, signer_contract: AccountId, key_version: u32) -> Result<(), FtWrapperError> {
+        crate::admin::set_mpc_signer(&mut self.state, signer_contract, key_version)
+    }
+
+    fn set_signature_request_timeout_internal(&mut self, timeout_ms: u64) -> Result<(), FtWrapperError> {
+        crate::admin::set_signature_request_timeout(&mut self.state, timeout_ms)
+    }
+
+    fn set_mpc_root_public_key_internal(&mut self, public_key: PublicKey) -> Result<(), FtWrapperError> {
+        crate::admin::set_mpc_root_public_key(&mut self.state, public_key)
+    }
+
+    fn derived_address_internal(&self, account_id: AccountId, derivation_path: String, chain: String) -> Result<String, FtWrapperError> {
+        crate::signer::derived_address(&self.state, account_id, derivation_path, chain)
+    }
+
     fn ft_balance_of_internal(&mut self, token: AccountId, account_id: AccountId) -> Promise {
-        crate::ft::ft_balance_of(&mut self.state, token, account_id)
+        crate::ft::ft_balance_of(&self.state, token, account_id)
     }
 
     fn handle_registration_internal(&mut self, token: AccountId, account_id: AccountId) -> Promise {
         crate::ft::handle_registration(&mut self.state, token, account_id)
     }
 
-    fn handle_storage_deposit_internal(&mut self, token: AccountId, account_id: AccountId) -> Promise {
-        crate::ft::handle_storage_deposit(&mut self.state, token, account_id)
+    fn handle_storage_deposit_internal(&mut self, token: AccountId, account_id: AccountId, amount: U128, payer: AccountId, balance: StorageBalance) -> bool {
+        crate::ft::handle_storage_deposit(&mut self.state, token, account_id, amount, payer, balance)
     }
 }
 
@@ -0,0 +1,401 @@
+use near_sdk::{env, serde_json, AccountId, Gas, NearToken, Promise, PromiseResult, PublicKey};
+use near_sdk::json_types::U128;
+use k256::elliptic_curve::ops::Reduce;
+use k256::elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint};
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use sha3::{Digest, Sha3_256};
+use bech32::hrp;
+use crate::state::FtWrapperContractState;
+use crate::types::{AddressFormat, BitcoinSpend, BitcoinTransaction, RequestChainSignatureArgs, UnsignedTransaction, SignRequest, SignatureRequest, SignatureRequestStatus, SignatureResponse};
+use crate::errors::FtWrapperError;
+use crate::events::FtWrapperEvent;
+use crate::ft::{CALLBACK_GAS, DEFAULT_PAGE_LIMIT};
+use crate::{ext_mpc_signer, ext_self};
+
+/// Prefix the MPC signer hashes together with the predecessor and path to get the key tweak.
+const EPSILON_DERIVATION_PREFIX: &str = "near-mpc-recovery v0.1.0 epsilon derivation:";
+
+/// Path passed to the MPC signer. The signer derives keys from the wrapper's account, so the
+/// requester is prefixed to keep each account's derived keys apart.
+pub fn signing_path(requester: &AccountId, derivation_path: &str) -> String {
+    format!("{},{}", requester, derivation_path)
+}
+
+/// Asks the MPC signer to sign `payload` for `target_chain`, forwarding the attached deposit
+/// as the signer's fee. The signature is stored against the returned request id.
+pub fn request_chain_signature(state: &mut FtWrapperContractState, args: RequestChainSignatureArgs) -> Result<Promise, FtWrapperError> {
+    state.assert_balance()?;
+    if !state.supported_tokens.contains(&args.token) {
+        return Err(FtWrapperError::TokenNotSupported);
+    }
+    request_signature(
+        state,
+        env::predecessor_account_id(),
+        args.target_chain,
+        args.derivation_path,
+        args.payload,
+        args.transaction,
+        env::attached_deposit(),
+    )
+}
+
+/// Records and submits a signature request on behalf of `requester`, paying the signer `deposit`.
+pub fn request_signature(
+    state: &mut FtWrapperContractState,
+    requester: AccountId,
+    target_chain: String,
+    derivation_path: String,
+    payload: Vec<u8>,
+    transaction: Option<UnsignedTransaction>,
+    deposit: NearToken,
+) -> Result<Promise, FtWrapperError> {
+    let address_format = crate::chains::enabled_chain(state, &target_chain)?.address_format;
+    // Requests are signed with the secp256k1 key; ed25519 chains such as Solana cannot use it
+    if !matches!(address_format, AddressFormat::Evm | AddressFormat::Bitcoin | AddressFormat::BitcoinTestnet) {
+        return Err(FtWrapperError::UnsupportedTransaction);
+    }
+    if transaction.is_some() && !payload.is_empty() {
+        return Err(FtWrapperError::InvalidSignaturePayload);
+    }
+    if deposit.is_zero() {
+        return Err(FtWrapperError::InvalidDeposit);
+    }
+    let (payload, unsigned_transaction) = match transaction {
+        Some(UnsignedTransaction::Bitcoin(tx)) => {
+            return request_bitcoin_signatures(state, address_format, requester, target_chain, derivation_path, tx, deposit);
+        }
+        Some(transaction) => {
+            if address_format != AddressFormat::Evm {
+                return Err(FtWrapperError::UnsupportedTransaction);
+            }
+            let (encoded, hash) = crate::evm::build_transaction(transaction)?;
+            (hash.to_vec(), Some(encoded))
+        }
+        None => (payload, None),
+    };
+    if payload.len() != 32 {
+        return Err(FtWrapperError::InvalidSignaturePayload);
+    }
+
+    let request_id = new_signature_request(state, requester, target_chain, derivation_path, payload, unsigned_transaction, None);
+    Ok(submit_signature_request(state, request_id, deposit))
+}
+
+/// Requests one signature per input of a segwit v0 spend from the requester's derived key,
+/// splitting the deposit between the `sign` calls.
+fn request_bitcoin_signatures(
+    state: &mut FtWrapperContractState,
+    address_format: AddressFormat,
+    requester: AccountId,
+    target_chain: String,
+    derivation_path: String,
+    tx: BitcoinTransaction,
+    deposit: NearToken,
+) -> Result<Promise, FtWrapperError> {
+    if !matches!(address_format, AddressFormat::Bitcoin | AddressFormat::BitcoinTestnet) {
+        return Err(FtWrapperError::UnsupportedTransaction);
+    }
+    let public_key = derived_secp256k1_key(state, &requester, &derivation_path)?
+        .to_encoded_point(true)
+        .as_bytes()
+        .to_vec();
+    let unsigned_transaction = crate::bitcoin::serialize_unsigned(address_format, &tx)?;
+    let sighashes = crate::bitcoin::segwit_v0_sighashes(&tx, address_format, &public_key)?;
+    let deposit = deposit.as_yoctonear();
+    let inputs = sighashes.len() as u128;
+    if deposit < inputs {
+        return Err(FtWrapperError::InvalidDeposit);
+    }
+    // All `sign` calls are scheduled from this call, so its gas caps the number of inputs
+    let required = sighashes.len() as u64 * (state.cross_contract_gas + CALLBACK_GAS.as_gas()) + CALLBACK_GAS.as_gas();
+    if Gas::from_gas(required) > env::prepaid_gas().saturating_sub(env::used_gas()) {
+        return Err(FtWrapperError::InsufficientGas);
+    }
+
+    let spend_id = state.next_bitcoin_spend_id;
+    state.next_bitcoin_spend_id += 1;
+    let signature_request_ids: Vec<u64> = sighashes.iter()
+        .map(|sighash| new_signature_request(state, requester.clone(), target_chain.clone(), derivation_path.clone(), sighash.to_vec(), None, Some(spend_id)))
+        .collect();
+    state.bitcoin_spends.insert(spend_id, BitcoinSpend {
+        id: spend_id,
+        requester,
+        unsigned_transaction,
+        public_key,
+        signature_request_ids: signature_request_ids.clone(),
+        signed_transaction: None,
+    });
+
+    let mut promise: Option<Promise> = None;
+    for (index, request_id) in signature_request_ids.into_iter().enumerate() {
+        let share = deposit / inputs + if index == 0 { deposit % inputs } else { 0 };
+        let sign = submit_signature_request(state, request_id, NearToken::from_yoctonear(share));
+        promise = Some(match promise {
+            Some(joint) => joint.and(sign),
+            None => sign,
+        });
+    }
+    Ok(promise.expect("At least one input").then(
+        ext_self::ext(env::current_account_id())
+            .with_static_gas(CALLBACK_GAS)
+            .handle_bitcoin_spend(spend_id),
+    ))
+}
+
+fn new_signature_request(
+    state: &mut FtWrapperContractState,
+    requester: AccountId,
+    target_chain: String,
+    derivation_path: String,
+    payload: Vec<u8>,
+    unsigned_transaction: Option<Vec<u8>>,
+    bitcoin_spend_id: Option<u64>,
+) -> u64 {
+    let request_id = state.next_signature_request_id;
+    state.next_signature_request_id += 1;
+    let now = env::block_timestamp_ms();
+    state.signature_requests.insert(request_id, SignatureRequest {
+        id: request_id,
+        requester: requester.clone(),
+        target_chain,
+        derivation_path,
+        payload,
+        unsigned_transaction,
+        bitcoin_spend_id,
+        status: SignatureRequestStatus::Pending,
+        signature: None,
+        attempts: 0,
+        deposit: U128(0),
+        created_at: now,
+        updated_at: now,
+    });
+    state.signature_requests_by_account
+        .entry(requester)
+        .or_default()
+        .push(request_id);
+    request_id
+}
+
+/// Sends a failed request to the signer again with a fresh deposit.
+pub fn retry_signature_request(state: &mut FtWrapperContractState, request_id: u64) -> Result<Promise, FtWrapperError> {
+    state.assert_balance()?;
+    let request = state.signature_requests.get(&request_id).ok_or(FtWrapperError::SignatureRequestNotFound)?;
+    if request.requester != env::predecessor_account_id() {
+        return Err(FtWrapperError::Unauthorized);
+    }
+    // An expired request is still waiting on its callback; retrying it before the callback
+    // ran would pay the signer twice for the same payload
+    if request.status != SignatureRequestStatus::Failed {
+        return Err(FtWrapperError::InvalidSignatureRequestStatus);
+    }
+    crate::chains::enabled_chain(state, &request.target_chain)?;
+    if env::attached_deposit().is_zero() {
+        return Err(FtWrapperError::InvalidDeposit);
+    }
+    Ok(submit_signature_request(state, request_id, env::attached_deposit()))
+}
+
+/// Starts a new `sign` attempt for a stored request and moves it back to `Pending`.
+fn submit_signature_request(state: &mut FtWrapperContractState, request_id: u64, deposit: NearToken) -> Promise {
+    let key_version = state.mpc_key_version;
+    let request = state.signature_requests.get_mut(&request_id).expect("Signature request not found");
+    request.status = SignatureRequestStatus::Pending;
+    request.attempts += 1;
+    request.deposit = U128(deposit.as_yoctonear());
+    request.updated_at = env::block_timestamp_ms();
+    let attempt = request.attempts;
+    let sign_request = SignRequest {
+        payload: request.payload.as_slice().try_into().expect("Payload length checked on request"),
+        path: signing_path(&request.requester, &request.derivation_path),
+        key_version,
+    };
+    FtWrapperEvent::SignatureRequested {
+        request_id,
+        requester: request.requester.clone(),
+        target_chain: request.target_chain.clone(),
+        derivation_path: request.derivation_path.clone(),
+    }.emit();
+
+    ext_mpc_signer::ext(state.mpc_signer_contract.clone())
+        .with_static_gas(Gas::from_gas(state.cross_contract_gas))
+        .with_attached_deposit(deposit)
+        .sign(sign_request)
+        .then(
+            ext_self::ext(env::current_account_id())
+                .with_static_gas(CALLBACK_GAS)
+                .handle_signature_result(request_id, attempt),
+        )
+}
+
+/// Returns the updated request, so the caller gets the signature next to the unsigned
+/// transaction it was made for.
+pub fn handle_signature_result(state: &mut FtWrapperContractState, request_id: u64, attempt: u32) -> Option<SignatureRequest> {
+    let result = env::promise_result(0);
+    let signature = match &result {
+        PromiseResult::Successful(value) => serde_json::from_slice::<SignatureResponse>(value).ok(),
+        _ => None,
+    };
+    let request = state.signature_requests.get_mut(&request_id)?;
+    // A retry has superseded this attempt
+    if request.attempts != attempt || request.status != SignatureRequestStatus::Pending {
+        return None;
+    }
+    request.updated_at = env::block_timestamp_ms();
+    let signature = match signature {
+        Some(signature) => signature,
+        None => {
+            request.status = SignatureRequestStatus::Failed;
+            // A failed `sign` call returns its fee to the wrapper; pass it on to the requester
+            if matches!(result, PromiseResult::Failed) && request.deposit.0 > 0 {
+                Promise::new(request.requester.clone()).transfer(NearToken::from_yoctonear(request.deposit.0));
+            }
+            env::log_str(&format!("Chain signature request {} failed", request_id));
+            return None;
+        }
+    };
+    request.status = SignatureRequestStatus::Signed;
+    request.signature = Some(signature);
+    FtWrapperEvent::SignatureIssued {
+        request_id,
+        requester: request.requester.clone(),
+    }.emit();
+    let request = request.clone();
+    if let Some(spend_id) = request.bitcoin_spend_id {
+        assemble_bitcoin_spend(state, spend_id);
+    }
+    Some(request)
+}
+
+/// Builds the signed transaction once every input of the spend has its signature.
+fn assemble_bitcoin_spend(state: &mut FtWrapperContractState, spend_id: u64) {
+    let spend = match state.bitcoin_spends.get(&spend_id) {
+        Some(spend) if spend.signed_transaction.is_none() => spend,
+        _ => return,
+    };
+    let signatures: Option<Vec<SignatureResponse>> = spend.signature_request_ids.iter()
+        .map(|id| state.signature_requests.get(id).and_then(|request| request.signature.clone()))
+        .collect();
+    let signatures = match signatures {
+        Some(signatures) => signatures,
+        None => return,
+    };
+    match crate::bitcoin::assemble_signed(&spend.unsigned_transaction, &spend.public_key, &signatures) {
+        Some(signed) => {
+            let mut txid = env::sha256_array(&env::sha256_array(&spend.unsigned_transaction));
+            txid.reverse();
+            let spend = state.bitcoin_spends.get_mut(&spend_id).expect("Spend exists");
+            spend.signed_transaction = Some(signed);
+            FtWrapperEvent::BitcoinTransactionSigned {
+                spend_id,
+                txid: crate::address::to_hex(&txid),
+            }.emit();
+        }
+        None => env::log_str(&format!("Bitcoin spend {} has a malformed signature", spend_id)),
+    }
+}
+
+/// Final step of a Bitcoin signing request: returns the spend with its assembled transaction.
+pub fn handle_bitcoin_spend(state: &FtWrapperContractState, spend_id: u64) -> Option<BitcoinSpend> {
+    state.bitcoin_spends.get(&spend_id).cloned()
+}
+
+pub fn get_bitcoin_spend(state: &FtWrapperContractState, spend_id: u64) -> Option<BitcoinSpend> {
+    state.bitcoin_spends.get(&spend_id).cloned()
+}
+
+/// A pending request the signer has not answered within the timeout is reported as expired.
+fn effective_status(state: &FtWrapperContractState, request: &SignatureRequest) -> SignatureRequestStatus {
+    if request.status == SignatureRequestStatus::Pending
+        && env::block_timestamp_ms() >= request.updated_at + state.signature_request_timeout_ms
+    {
+        return SignatureRequestStatus::Expired;
+    }
+    request.status
+}
+
+fn with_effective_status(state: &FtWrapperContractState, request: &SignatureRequest) -> SignatureRequest {
+    let mut request = request.clone();
+    request.status = effective_status(state, &request);
+    request
+}
+
+pub fn get_signature_request(state: &FtWrapperContractState, request_id: u64) -> Option<SignatureRequest> {
+    state.signature_requests.get(&request_id)
+        .map(|request| with_effective_status(state, request))
+}
+
+pub fn get_signature_requests_by_account(
+    state: &FtWrapperContractState,
+    account_id: AccountId,
+    from_index: Option<u64>,
+    limit: Option<u64>,
+) -> Vec<SignatureRequest> {
+    let ids = match state.signature_requests_by_account.get(&account_id) {
+        Some(ids) => ids,
+        None => return Vec::new(),
+    };
+    ids.iter()
+        .skip(from_index.unwrap_or(0) as usize)
+        .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+        .filter_map(|id| state.signature_requests.get(id))
+        .map(|request| with_effective_status(state, request))
+        .collect()
+}
+
+/// Tweak the MPC signer adds to its root key for keys requested by `predecessor` with `path`.
+fn derive_epsilon(predecessor: &AccountId, path: &str) -> [u8; 32] {
+    let mut hasher = Sha3_256::new();
+    hasher.update(format!("{}{},{}", EPSILON_DERIVATION_PREFIX, predecessor, path));
+    hasher.finalize().into()
+}
+
+pub(crate) fn secp256k1_point(public_key: &PublicKey) -> Result<k256::AffinePoint, FtWrapperError> {
+    let encoded = k256::EncodedPoint::from_untagged_bytes(public_key.as_bytes()[1..].into());
+    Option::from(k256::AffinePoint::from_encoded_point(&encoded)).ok_or(FtWrapperError::InvalidMpcRootKey)
+}
+
+pub(crate) fn ed25519_point(public_key: &PublicKey) -> Result<EdwardsPoint, FtWrapperError> {
+    let bytes: [u8; 32] = public_key.as_bytes()[1..].try_into().map_err(|_| FtWrapperError::InvalidMpcRootKey)?;
+    CompressedEdwardsY(bytes).decompress().ok_or(FtWrapperError::InvalidMpcRootKey)
+}
+
+/// secp256k1 key the MPC signer uses for `account_id` and `derivation_path`.
+pub(crate) fn derived_secp256k1_key(
+    state: &FtWrapperContractState,
+    account_id: &AccountId,
+    derivation_path: &str,
+) -> Result<k256::AffinePoint, FtWrapperError> {
+    let root = state.mpc_root_secp256k1_key.as_ref().ok_or(FtWrapperError::MpcRootKeyMissing)?;
+    let epsilon = derive_epsilon(&env::current_account_id(), &signing_path(account_id, derivation_path));
+    let tweak = <k256::Scalar as Reduce<k256::U256>>::reduce_bytes(&epsilon.into());
+    Ok((k256::ProjectivePoint::GENERATOR * tweak + secp256k1_point(root)?).to_affine())
+}
+
+/// Address on `chain` of the key the MPC signer uses for `account_id` and `derivation_path`
+/// when the request goes through this contract. Only chains whose requests the wrapper can
+/// sign have one, so no funds are sent to an address nothing can spend from.
+pub fn derived_address(
+    state: &FtWrapperContractState,
+    account_id: AccountId,
+    derivation_path: String,
+    chain: String,
+) -> Result<String, FtWrapperError> {
+    let format = state.chains.get(&chain).ok_or(FtWrapperError::UnknownChain)?.address_format;
+    match format {
+        // Requests are only signed with the secp256k1 key, see `request_signature`
+        AddressFormat::Solana => Err(FtWrapperError::UnsupportedTransaction),
+        AddressFormat::Evm | AddressFormat::Bitcoin | AddressFormat::BitcoinTestnet => {
+            let derived = derived_secp256k1_key(state, &account_id, &derivation_path)?;
+            Ok(match format {
+                AddressFormat::Evm => {
+                    let uncompressed = derived.to_encoded_point(false);
+                    crate::address::evm_address(uncompressed.as_bytes()[1..].try_into().unwrap())
+                }
+                AddressFormat::Bitcoin => {
+                    crate::address::bitcoin_p2wpkh_address(derived.to_encoded_point(true).as_bytes().try_into().unwrap(), hrp::BC)
+                }
+                _ => crate::address::bitcoin_p2wpkh_address(derived.to_encoded_point(true).as_bytes().try_into().unwrap(), hrp::TB),
+            })
+        }
+    }
+}
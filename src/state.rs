@@ -1,10 +1,10 @@
-use near_sdk::{AccountId, env};
-use near_sdk::store::LookupMap;
+use near_sdk::{AccountId, PublicKey, env};
+use near_sdk::store::{IterableMap, IterableSet, LazyOption, LookupMap, LookupSet};
 use near_sdk::json_types::U128;
 use near_sdk::borsh::{self, BorshSerialize, BorshDeserialize};
 use near_sdk_macros::NearSchema;
 use crate::errors::FtWrapperError;
-use crate::types::StorageBalance;
+use crate::types::{StorageBalance, BridgeTransfer, BridgeTransferStatus, BridgedAsset, ChainConfig, SignatureRequest, BitcoinSpend, ActionBatch, IntentKey, RegistrationStatus, StorageSponsorship};
 
 #[derive(BorshSerialize, BorshDeserialize, NearSchema)]
 #[abi(borsh)]
@@ -19,12 +19,46 @@ pub struct FtWrapperContractState {
     pub min_balance: u128,
     pub max_balance: u128,
     pub fee_percentage: u64, // Added for 0.1.1
+    pub mpc_public_keys: Vec<PublicKey>, // Added for 0.2.0
+    pub processed_messages: LookupSet<[u8; 32]>,
+    /// Per token, the amount held against outbound transfers: pending or refundable ones, and
+    /// for native tokens also completed ones until finalize_transfer releases them back
+    pub locked_balances: LookupMap<AccountId, u128>,
+    pub bridge_transfers: IterableMap<u64, BridgeTransfer>,
+    pub bridge_transfers_by_sender: LookupMap<AccountId, Vec<u64>>,
+    /// Ids of the bridge transfers currently in each status
+    pub bridge_transfers_by_status: LookupMap<BridgeTransferStatus, IterableSet<u64>>,
+    pub next_bridge_transfer_id: u64,
+    pub bridge_refund_timeout_ms: u64,
+    pub bridged_token_code: LazyOption<Vec<u8>>,
+    pub bridged_tokens: LookupMap<String, AccountId>,
+    pub bridged_token_origins: LookupMap<AccountId, BridgedAsset>,
+    pub chains: IterableMap<String, ChainConfig>,
+    pub mpc_signer_contract: AccountId,
+    pub mpc_key_version: u32,
+    pub signature_requests: LookupMap<u64, SignatureRequest>,
+    pub next_signature_request_id: u64,
+    pub signature_requests_by_account: LookupMap<AccountId, Vec<u64>>,
+    pub signature_request_timeout_ms: u64,
+    pub mpc_root_secp256k1_key: Option<PublicKey>,
+    pub mpc_root_ed25519_key: Option<PublicKey>,
+    pub bitcoin_spends: LookupMap<u64, BitcoinSpend>,
+    pub next_bitcoin_spend_id: u64,
+    pub action_batches: LookupMap<u64, ActionBatch>,
+    pub next_action_batch_id: u64,
+    pub registrations: LookupMap<(AccountId, AccountId), RegistrationStatus>,
+    pub storage_sponsorship: StorageSponsorship,
+    /// Tokens each account deposited through `ft_on_transfer`, keyed by (token, account)
+    pub balances: LookupMap<(AccountId, AccountId), u128>,
+    pub intent_keys: LookupMap<AccountId, Vec<IntentKey>>,
+    /// Last delegate action nonce used by each account, shared by all of its keys
+    pub intent_nonces: LookupMap<AccountId, u64>,
 }
 
 impl FtWrapperContractState {
     pub fn new(manager: AccountId, relayer_contract: AccountId, storage_deposit: U128) -> Self {
         Self {
-            version: "0.1.1".to_string(), // Updated to 0.1.1
+            version: "0.2.0".to_string(), // Updated to 0.2.0
             manager,
             relayer_contract,
             supported_tokens: Vec::new(),
@@ -34,6 +68,35 @@ impl FtWrapperContractState {
             min_balance: 10_000_000_000_000_000_000_000_000,
             max_balance: 1_000_000_000_000_000_000_000_000_000,
             fee_percentage: 0, // Default value
+            mpc_public_keys: Vec::new(),
+            processed_messages: LookupSet::new(b"p".to_vec()),
+            locked_balances: LookupMap::new(b"l".to_vec()),
+            bridge_transfers: IterableMap::new(b"b".to_vec()),
+            bridge_transfers_by_sender: LookupMap::new(b"t".to_vec()),
+            bridge_transfers_by_status: LookupMap::new(b"y".to_vec()),
+            next_bridge_transfer_id: 0,
+            bridge_refund_timeout_ms: 86_400_000, // 24 hours
+            bridged_token_code: LazyOption::new(b"c".to_vec(), None),
+            bridged_tokens: LookupMap::new(b"f".to_vec()),
+            bridged_token_origins: LookupMap::new(b"o".to_vec()),
+            chains: IterableMap::new(b"r".to_vec()),
+            mpc_signer_contract: "v1.signer".parse().unwrap(),
+            mpc_key_version: 0,
+            signature_requests: LookupMap::new(b"g".to_vec()),
+            next_signature_request_id: 0,
+            signature_requests_by_account: LookupMap::new(b"q".to_vec()),
+            signature_request_timeout_ms: 600_000, // 10 minutes
+            mpc_root_secp256k1_key: None,
+            mpc_root_ed25519_key: None,
+            bitcoin_spends: LookupMap::new(b"w".to_vec()),
+            next_bitcoin_spend_id: 0,
+            action_batches: LookupMap::new(b"x".to_vec()),
+            next_action_batch_id: 0,
+            registrations: LookupMap::new(b"e".to_vec()),
+            storage_sponsorship: StorageSponsorship::Sponsored,
+            balances: LookupMap::new(b"d".to_vec()),
+            intent_keys: LookupMap::new(b"i".to_vec()),
+            intent_nonces: LookupMap::new(b"n".to_vec()),
         }
     }
 
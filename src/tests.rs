@@ -1,13 +1,17 @@
+#![allow(clippy::module_inception)]
+
 #[cfg(test)]
 mod tests {
     use crate::{FtWrapperContract, state_versions::{StateV010, StateV011}};
+    use crate::errors::FtWrapperError;
+    use crate::types::{AddressFormat, RegistrationStatus, StorageBalance, StorageSponsorship, BridgeTransferArgs, FtTransferArgs, BridgeMessage, BridgeTransferStatus, BridgedTokenMetadata, ChainConfig, FinalizeTransferArgs, RequestChainSignatureArgs, SignatureRequestStatus, EvmTransaction, Erc20Transfer, UnsignedTransaction, BitcoinTransaction, BitcoinInput, BitcoinOutput, BitcoinScriptType, Action, ActionOutcome, ActionStatus, DelegateAction, SignedDelegateAction, BRIDGE_MESSAGE_VERSION};
     use near_sdk::{
         env,
         test_utils::{VMContextBuilder, get_logs},
-        testing_env, AccountId, NearToken,
+        testing_env, AccountId, CurveType, Gas, NearToken, PromiseOrValue, PromiseResult, PublicKey, VMContext,
     };
     use near_sdk::store::LookupMap;
-    use near_sdk::json_types::U128;
+    use near_sdk::json_types::{U128, U64};
     use near_sdk::borsh;
 
     fn setup_context(predecessor: AccountId) -> VMContextBuilder {
@@ -20,6 +24,120 @@ mod tests {
         context
     }
 
+    fn ethereum_chain() -> ChainConfig {
+        ChainConfig {
+            chain_id: "ethereum".to_string(),
+            display_name: "Ethereum".to_string(),
+            address_format: AddressFormat::Evm,
+            min_amount: U128(1),
+            max_amount: U128(1_000_000),
+            enabled: true,
+            required_confirmations: 12,
+        }
+    }
+
+    /// Creates a contract with the ethereum chain already registered.
+    fn new_contract(manager: &AccountId) -> FtWrapperContract {
+        let mut contract = FtWrapperContract::new(
+            manager.clone(),
+            "relayer.testnet".parse().unwrap(),
+            U128(1_250_000_000_000_000_000_000),
+        );
+        contract.state.chains.insert("ethereum".to_string(), ethereum_chain());
+        contract
+    }
+
+    fn testing_env_with_result(context: VMContext, result: PromiseResult) {
+        testing_env!(
+            context,
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result],
+        );
+    }
+
+    /// Lists `(receiver, method)` for every function call scheduled by the last contract call.
+    fn created_function_calls() -> Vec<(AccountId, String)> {
+        near_sdk::test_utils::get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| {
+                let receiver = receipt.receiver_id.clone();
+                receipt.actions.into_iter().filter_map(move |action| match action {
+                    near_sdk::mock::MockAction::FunctionCallWeight { method_name, .. } => {
+                        Some((receiver.clone(), String::from_utf8(method_name).unwrap()))
+                    }
+                    _ => None,
+                })
+            })
+            .collect()
+    }
+
+    /// Amounts of the NEAR transfers to `receiver` scheduled by the last contract call.
+    fn created_transfers(receiver: &AccountId) -> Vec<u128> {
+        near_sdk::test_utils::get_created_receipts()
+            .into_iter()
+            .filter(|receipt| &receipt.receiver_id == receiver)
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                near_sdk::mock::MockAction::Transfer { deposit, .. } => Some(deposit.as_yoctonear()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Sets up a contract with `token` supported and one outbound transfer of `amount` from alice.
+    fn contract_with_bridge_transfer(token: &AccountId, amount: u128) -> FtWrapperContract {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+
+        testing_env!(setup_context(token.clone()).build());
+        let msg = r#"{"destination_chain":"ethereum","recipient":"0x52908400098527886E0F7030069857D2E4169EE7"}"#;
+        contract.ft_on_transfer("alice.testnet".parse().unwrap(), U128(amount), msg.to_string());
+        contract
+    }
+
+    fn ed25519_signing_key() -> ed25519_dalek::SigningKey {
+        ed25519_dalek::SigningKey::from_bytes(&[7u8; 32])
+    }
+
+    fn ed25519_public_key(signing_key: &ed25519_dalek::SigningKey) -> PublicKey {
+        PublicKey::from_parts(CurveType::ED25519, signing_key.verifying_key().to_bytes().to_vec()).unwrap()
+    }
+
+    fn bridge_message(token: AccountId) -> BridgeMessage {
+        BridgeMessage {
+            version: BRIDGE_MESSAGE_VERSION,
+            source_chain: "ethereum".to_string(),
+            nonce: 1,
+            token,
+            recipient: "alice.testnet".parse().unwrap(),
+            amount: U128(1_000),
+            is_native: true,
+            expires_at: 2_000_000,
+        }
+    }
+
+    fn finalize_args(message: &BridgeMessage, signature: Vec<u8>, message_payload: Vec<u8>) -> FinalizeTransferArgs {
+        FinalizeTransferArgs {
+            token: message.token.clone(),
+            recipient: message.recipient.clone(),
+            amount: message.amount,
+            source_chain: message.source_chain.clone(),
+            is_native: message.is_native,
+            signature,
+            message_payload,
+        }
+    }
+
+    fn signed_finalize_args(signing_key: &ed25519_dalek::SigningKey, message: &BridgeMessage) -> FinalizeTransferArgs {
+        let payload = borsh::to_vec(message).unwrap();
+        let signature = ed25519_dalek::Signer::sign(signing_key, &payload).to_bytes().to_vec();
+        finalize_args(message, signature, payload)
+    }
+
     #[test]
     fn test_add_supported_token() {
         let manager: AccountId = "manager.testnet".parse().unwrap();
@@ -44,7 +162,7 @@ mod tests {
     }
 
     #[test]
-    fn test_migration_from_010_to_011() {
+    fn test_migration_from_010_to_020() {
         let manager: AccountId = "manager.testnet".parse().unwrap();
         let context = setup_context(manager.clone());
         testing_env!(context.build());
@@ -67,7 +185,7 @@ mod tests {
 
         let new_contract = FtWrapperContract::migrate();
 
-        assert_eq!(new_contract.state.version, "0.1.1", "Version should be 0.1.1");
+        assert_eq!(new_contract.state.version, "0.2.0", "Version should be 0.2.0");
         assert_eq!(new_contract.state.manager, manager, "Manager should be preserved");
         assert_eq!(
             new_contract.state.relayer_contract,
@@ -86,13 +204,13 @@ mod tests {
             "Expected migration log, got: {:?}", logs
         );
         assert!(
-            logs.contains(&"EVENT_JSON:{\"standard\":\"nep297\",\"version\":\"1.0.0\",\"event\":\"state_migrated\",\"data\":{\"old_version\":\"0.1.0\",\"new_version\":\"0.1.1\"}}".to_string()),
+            logs.contains(&"EVENT_JSON:{\"standard\":\"nep297\",\"version\":\"1.0.0\",\"event\":\"state_migrated\",\"data\":{\"old_version\":\"0.1.0\",\"new_version\":\"0.2.0\"}}".to_string()),
             "Expected state_migrated event, got: {:?}", logs
         );
     }
 
     #[test]
-    fn test_migration_from_011_to_011() {
+    fn test_migration_from_011_to_020() {
         let manager: AccountId = "manager.testnet".parse().unwrap();
         let context = setup_context(manager.clone());
         testing_env!(context.build());
@@ -116,7 +234,7 @@ mod tests {
 
         let new_contract = FtWrapperContract::migrate();
 
-        assert_eq!(new_contract.state.version, "0.1.1", "Version should be 0.1.1");
+        assert_eq!(new_contract.state.version, "0.2.0", "Version should be 0.2.0");
         assert_eq!(new_contract.state.manager, manager, "Manager should be preserved");
         assert_eq!(
             new_contract.state.relayer_contract,
@@ -128,6 +246,37 @@ mod tests {
             "Supported tokens should be preserved"
         );
         assert_eq!(new_contract.state.fee_percentage, 10, "Fee percentage should be preserved");
+        assert!(new_contract.state.mpc_public_keys.is_empty(), "MPC keys should be initialized");
+
+        let logs = get_logs();
+        assert!(
+            logs.contains(&"Migrating from state version 0.1.1".to_string()),
+            "Expected migration log, got: {:?}", logs
+        );
+        assert!(
+            logs.contains(&"EVENT_JSON:{\"standard\":\"nep297\",\"version\":\"1.0.0\",\"event\":\"state_migrated\",\"data\":{\"old_version\":\"0.1.1\",\"new_version\":\"0.2.0\"}}".to_string()),
+            "Expected state_migrated event, got: {:?}", logs
+        );
+    }
+
+    #[test]
+    fn test_migration_already_latest() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let context = setup_context(manager.clone());
+        testing_env!(context.build());
+
+        let contract = FtWrapperContract::new(
+            manager.clone(),
+            "relayer.testnet".parse().unwrap(),
+            U128(1_250_000_000_000_000_000_000),
+        );
+        let state_bytes = borsh::to_vec(&contract.state).expect("Failed to serialize state");
+        env::state_write(&state_bytes);
+
+        let new_contract = FtWrapperContract::migrate();
+
+        assert_eq!(new_contract.state.version, "0.2.0", "Version should be 0.2.0");
+        assert_eq!(new_contract.state.manager, manager, "Manager should be preserved");
 
         let logs = get_logs();
         assert!(
@@ -144,7 +293,7 @@ mod tests {
 
         let new_contract = FtWrapperContract::migrate();
 
-        assert_eq!(new_contract.state.version, "0.1.1", "Version should be 0.1.1");
+        assert_eq!(new_contract.state.version, "0.2.0", "Version should be 0.2.0");
         assert_eq!(new_contract.state.manager, env::current_account_id(), "Manager should be current account");
         assert_eq!(new_contract.state.fee_percentage, 0, "Fee percentage should be initialized");
 
@@ -166,7 +315,7 @@ mod tests {
 
         let new_contract = FtWrapperContract::migrate();
 
-        assert_eq!(new_contract.state.version, "0.1.1", "Version should be 0.1.1");
+        assert_eq!(new_contract.state.version, "0.2.0", "Version should be 0.2.0");
         assert_eq!(new_contract.state.manager, env::current_account_id(), "Manager should be current account");
         assert_eq!(new_contract.state.fee_percentage, 0, "Fee percentage should be initialized");
 
@@ -176,4 +325,2257 @@ mod tests {
             "Expected no prior state log, got: {:?}", logs
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_mpc_key_lifecycle() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let context = setup_context(manager.clone());
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&manager);
+        let first = ed25519_public_key(&ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]));
+        let second = ed25519_public_key(&ed25519_dalek::SigningKey::from_bytes(&[2u8; 32]));
+
+        contract.add_mpc_public_key(first.clone()).expect("Failed to add key");
+        assert!(matches!(
+            contract.add_mpc_public_key(first.clone()),
+            Err(FtWrapperError::MpcKeyAlreadyExists)
+        ));
+        contract.rotate_mpc_public_key(first.clone(), second.clone()).expect("Failed to rotate key");
+        assert_eq!(contract.get_mpc_public_keys(), vec![second.clone()]);
+        assert!(matches!(
+            contract.retire_mpc_public_key(first.clone()),
+            Err(FtWrapperError::MpcKeyNotFound)
+        ));
+        contract.retire_mpc_public_key(second.clone()).expect("Failed to retire key");
+        assert!(contract.get_mpc_public_keys().is_empty());
+
+        let logs = get_logs();
+        let first_str = String::from(&first);
+        let second_str = String::from(&second);
+        assert!(
+            logs.contains(&format!("EVENT_JSON:{{\"standard\":\"nep297\",\"version\":\"1.0.0\",\"event\":\"mpc_key_added\",\"data\":{{\"public_key\":\"{}\"}}}}", first_str)),
+            "Expected mpc_key_added event, got: {:?}", logs
+        );
+        assert!(
+            logs.contains(&format!("EVENT_JSON:{{\"standard\":\"nep297\",\"version\":\"1.0.0\",\"event\":\"mpc_key_rotated\",\"data\":{{\"old_public_key\":\"{}\",\"new_public_key\":\"{}\"}}}}", first_str, second_str)),
+            "Expected mpc_key_rotated event, got: {:?}", logs
+        );
+        assert!(
+            logs.contains(&format!("EVENT_JSON:{{\"standard\":\"nep297\",\"version\":\"1.0.0\",\"event\":\"mpc_key_retired\",\"data\":{{\"public_key\":\"{}\"}}}}", second_str)),
+            "Expected mpc_key_retired event, got: {:?}", logs
+        );
+    }
+
+    #[test]
+    fn test_add_mpc_public_key_unauthorized() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let context = setup_context("alice.testnet".parse().unwrap());
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&manager);
+        let key = ed25519_public_key(&ed25519_signing_key());
+
+        assert!(matches!(contract.add_mpc_public_key(key), Err(FtWrapperError::Unauthorized)));
+        assert!(contract.get_mpc_public_keys().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidSignature")]
+    fn test_finalize_transfer_rejects_unsigned_payload() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let context = setup_context(manager.clone());
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&manager);
+        let token: AccountId = "token.testnet".parse().unwrap();
+        contract.add_supported_token(token.clone()).unwrap();
+        contract.add_mpc_public_key(ed25519_public_key(&ed25519_signing_key())).unwrap();
+
+        let forged = ed25519_dalek::SigningKey::from_bytes(&[9u8; 32]);
+        contract.finalize_transfer(signed_finalize_args(&forged, &bridge_message(token)));
+    }
+
+    #[test]
+    fn test_finalize_transfer_with_ed25519_signature() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let mut context = setup_context(manager.clone());
+        testing_env!(context.prepaid_gas(Gas::from_tgas(300)).build());
+
+        let mut contract = new_contract(&manager);
+        let token: AccountId = "token.testnet".parse().unwrap();
+        contract.add_supported_token(token.clone()).unwrap();
+        contract.state.locked_balances.insert(token.clone(), 1_000);
+        let signing_key = ed25519_signing_key();
+        contract.add_mpc_public_key(ed25519_public_key(&signing_key)).unwrap();
+
+        let args = signed_finalize_args(&signing_key, &bridge_message(token.clone()));
+        contract.finalize_transfer(args.clone());
+
+        // Native tokens are released with the one yocto NEP-141 requires
+        let release_deposits: Vec<NearToken> = near_sdk::test_utils::get_created_receipts()
+            .into_iter()
+            .filter(|receipt| receipt.receiver_id == token)
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                near_sdk::mock::MockAction::FunctionCallWeight { method_name, attached_deposit, .. } if method_name == b"ft_transfer" => Some(attached_deposit),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(release_deposits, vec![NearToken::from_yoctonear(1)]);
+        assert!(!get_logs().iter().any(|log| log.contains("\"event\":\"transfer_finalized\"")));
+
+        let current: AccountId = "ft-wrapper.testnet".parse().unwrap();
+        testing_env_with_result(setup_context(current).build(), PromiseResult::Successful(vec![]));
+        let message_id = crate::ft::message_id(&args.message_payload);
+        assert!(contract.handle_finalize_transfer(message_id, bridge_message(token), U128(0)));
+
+        let logs = get_logs();
+        assert!(
+            logs.iter().any(|log| log.contains("\"event\":\"transfer_finalized\"")),
+            "Expected transfer_finalized event, got: {:?}", logs
+        );
+    }
+
+    #[test]
+    fn test_finalize_transfer_with_secp256k1_signature() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let mut context = setup_context(manager.clone());
+        testing_env!(context.prepaid_gas(Gas::from_tgas(300)).build());
+
+        let mut contract = new_contract(&manager);
+        let token: AccountId = "token.testnet".parse().unwrap();
+        contract.add_supported_token(token.clone()).unwrap();
+        contract.state.locked_balances.insert(token.clone(), 1_000);
+
+        let signing_key = k256::ecdsa::SigningKey::from_slice(&[3u8; 32]).unwrap();
+        let encoded = signing_key.verifying_key().to_encoded_point(false);
+        let public_key = PublicKey::from_parts(CurveType::SECP256K1, encoded.as_bytes()[1..].to_vec()).unwrap();
+        contract.add_mpc_public_key(public_key).unwrap();
+
+        let message = bridge_message(token);
+        let payload = borsh::to_vec(&message).unwrap();
+        let hash = env::keccak256_array(&payload);
+        let (signature, recovery_id) = signing_key.sign_prehash_recoverable(&hash).unwrap();
+        let mut signature = signature.to_bytes().to_vec();
+        signature.push(recovery_id.to_byte() + 27);
+
+        contract.finalize_transfer(finalize_args(&message, signature, payload.clone()));
+
+        let current: AccountId = "ft-wrapper.testnet".parse().unwrap();
+        testing_env_with_result(setup_context(current).build(), PromiseResult::Successful(vec![]));
+        assert!(contract.handle_finalize_transfer(crate::ft::message_id(&payload), message, U128(0)));
+
+        let logs = get_logs();
+        assert!(
+            logs.iter().any(|log| log.contains("\"event\":\"transfer_finalized\"")),
+            "Expected transfer_finalized event, got: {:?}", logs
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "MessageAlreadyProcessed")]
+    fn test_finalize_transfer_rejects_replay() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let mut context = setup_context(manager.clone());
+        testing_env!(context.prepaid_gas(Gas::from_tgas(300)).build());
+
+        let mut contract = new_contract(&manager);
+        let token: AccountId = "token.testnet".parse().unwrap();
+        contract.add_supported_token(token.clone()).unwrap();
+        // Enough for the replay to reach the processed-message check
+        contract.state.locked_balances.insert(token.clone(), 2_000);
+        let signing_key = ed25519_signing_key();
+        contract.add_mpc_public_key(ed25519_public_key(&signing_key)).unwrap();
+
+        let args = signed_finalize_args(&signing_key, &bridge_message(token));
+
+        assert!(!contract.is_message_processed(args.message_payload.clone()));
+        contract.finalize_transfer(args.clone());
+        assert!(contract.is_message_processed(args.message_payload.clone()));
+
+        testing_env!(context.prepaid_gas(Gas::from_tgas(300)).build());
+        contract.finalize_transfer(args);
+    }
+
+    #[test]
+    fn test_failed_finalize_transfer_releases_message() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let current: AccountId = "ft-wrapper.testnet".parse().unwrap();
+        let mut context = setup_context(manager.clone());
+        testing_env!(context.prepaid_gas(Gas::from_tgas(300)).build());
+
+        let mut contract = new_contract(&manager);
+        let token: AccountId = "token.testnet".parse().unwrap();
+        contract.add_supported_token(token.clone()).unwrap();
+        contract.state.locked_balances.insert(token.clone(), 1_000);
+        let signing_key = ed25519_signing_key();
+        contract.add_mpc_public_key(ed25519_public_key(&signing_key)).unwrap();
+
+        let message = bridge_message(token);
+        let args = signed_finalize_args(&signing_key, &message);
+        let message_id = crate::ft::message_id(&args.message_payload);
+        contract.finalize_transfer(args.clone());
+        assert!(created_function_calls().iter().any(|(receiver, method)| receiver == &current && method == "handle_finalize_transfer"));
+
+        testing_env_with_result(setup_context(current.clone()).build(), PromiseResult::Successful(vec![]));
+        assert!(contract.handle_finalize_transfer(message_id, message.clone(), U128(0)));
+        assert!(contract.is_message_processed(args.message_payload.clone()));
+
+        testing_env_with_result(setup_context(current).build(), PromiseResult::Failed);
+        assert!(!contract.handle_finalize_transfer(message_id, message, U128(0)));
+        assert!(!get_logs().iter().any(|log| log.contains("\"event\":\"transfer_finalized\"")));
+        assert!(!contract.is_message_processed(args.message_payload.clone()));
+
+        // The message can be submitted again once the failed attempt released it
+        testing_env!(context.prepaid_gas(Gas::from_tgas(300)).build());
+        contract.finalize_transfer(args.clone());
+        assert!(contract.is_message_processed(args.message_payload));
+    }
+
+    #[test]
+    fn test_finalize_transfer_pays_fee_in_token() {
+        let token: AccountId = "token.testnet".parse().unwrap();
+        let current: AccountId = "ft-wrapper.testnet".parse().unwrap();
+        let relayer: AccountId = "relayer.testnet".parse().unwrap();
+        let mut contract = contract_with_bridge_transfer(&token, 5_000);
+        contract.state.fee_percentage = 100;
+
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).prepaid_gas(Gas::from_tgas(300)).build());
+        let signing_key = ed25519_signing_key();
+        contract.add_mpc_public_key(ed25519_public_key(&signing_key)).unwrap();
+        let message = bridge_message(token.clone());
+        let args = signed_finalize_args(&signing_key, &message);
+        let message_id = crate::ft::message_id(&args.message_payload);
+        contract.finalize_transfer(args.clone());
+        // No NEAR leaves the wrapper for the fee
+        assert!(created_transfers(&relayer).is_empty());
+
+        // A failed release pays nothing, so resubmitting the message cannot pay the fee twice
+        testing_env_with_result(setup_context(current.clone()).build(), PromiseResult::Failed);
+        assert!(!contract.handle_finalize_transfer(message_id, message.clone(), U128(10)));
+        assert_eq!(contract.get_balance(token.clone(), relayer.clone()), U128(0));
+
+        testing_env!(setup_context(manager).prepaid_gas(Gas::from_tgas(300)).build());
+        contract.finalize_transfer(args);
+        testing_env_with_result(setup_context(current).build(), PromiseResult::Successful(vec![]));
+        assert!(contract.handle_finalize_transfer(message_id, message, U128(10)));
+        assert!(created_transfers(&relayer).is_empty());
+        assert_eq!(contract.get_balance(token, relayer), U128(10));
+    }
+
+    #[test]
+    #[should_panic(expected = "BridgeMessageMismatch")]
+    fn test_finalize_transfer_rejects_mismatched_fields() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let context = setup_context(manager.clone());
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&manager);
+        let token: AccountId = "token.testnet".parse().unwrap();
+        contract.add_supported_token(token.clone()).unwrap();
+        contract.state.locked_balances.insert(token.clone(), 1_000);
+        let signing_key = ed25519_signing_key();
+        contract.add_mpc_public_key(ed25519_public_key(&signing_key)).unwrap();
+
+        let mut args = signed_finalize_args(&signing_key, &bridge_message(token));
+        args.amount = U128(1_000_000);
+
+        contract.finalize_transfer(args);
+    }
+
+    #[test]
+    #[should_panic(expected = "BridgeMessageExpired")]
+    fn test_finalize_transfer_rejects_expired_message() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let context = setup_context(manager.clone());
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&manager);
+        let token: AccountId = "token.testnet".parse().unwrap();
+        contract.add_supported_token(token.clone()).unwrap();
+        contract.state.locked_balances.insert(token.clone(), 1_000);
+        let signing_key = ed25519_signing_key();
+        contract.add_mpc_public_key(ed25519_public_key(&signing_key)).unwrap();
+
+        let mut message = bridge_message(token);
+        message.expires_at = env::block_timestamp_ms();
+
+        contract.finalize_transfer(signed_finalize_args(&signing_key, &message));
+    }
+
+    #[test]
+    fn test_ft_on_transfer_locks_tokens_for_bridge() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        let context = setup_context(manager.clone());
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+
+        let context = setup_context(token.clone());
+        testing_env!(context.build());
+        let sender: AccountId = "alice.testnet".parse().unwrap();
+        let msg = r#"{"destination_chain":"ethereum","recipient":"0x52908400098527886E0F7030069857D2E4169EE7"}"#;
+        let result = contract.ft_on_transfer(sender.clone(), U128(500), msg.to_string());
+
+        assert!(matches!(result, near_sdk::PromiseOrValue::Value(U128(0))), "All tokens should be kept");
+        assert_eq!(contract.get_locked_balance(token.clone()), U128(500));
+        let transfer = contract.state.bridge_transfers.get(&0).expect("Transfer should be recorded");
+        assert_eq!(transfer.sender, sender);
+        assert_eq!(transfer.amount, U128(500));
+
+        let logs = get_logs();
+        assert!(
+            logs.contains(&"EVENT_JSON:{\"standard\":\"nep297\",\"version\":\"1.0.0\",\"event\":\"bridge_transfer_initiated\",\"data\":{\"transfer_id\":0,\"token\":\"token.testnet\",\"sender\":\"alice.testnet\",\"amount\":\"500\",\"destination_chain\":\"ethereum\",\"recipient\":\"0x52908400098527886E0F7030069857D2E4169EE7\"}}".to_string()),
+            "Expected bridge_transfer_initiated event, got: {:?}", logs
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "InvalidTransferMessage")]
+    fn test_ft_on_transfer_rejects_invalid_msg() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        let context = setup_context(manager.clone());
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+
+        let context = setup_context(token);
+        testing_env!(context.build());
+        contract.ft_on_transfer("alice.testnet".parse().unwrap(), U128(500), "not json".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Transfer handling failed: TokenNotSupported")]
+    fn test_ft_on_transfer_deposit_of_unsupported_token() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+
+        testing_env!(setup_context("token.testnet".parse().unwrap()).build());
+        contract.ft_on_transfer("alice.testnet".parse().unwrap(), U128(500), String::new());
+    }
+
+    #[test]
+    fn test_bridge_transfer_ledger_views() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        let context = setup_context(manager.clone());
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+
+        let context = setup_context(token.clone());
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let bob: AccountId = "bob.testnet".parse().unwrap();
+        let msg = r#"{"destination_chain":"ethereum","recipient":"0x52908400098527886E0F7030069857D2E4169EE7"}"#;
+        for (sender, amount) in [(&alice, 100), (&bob, 200), (&alice, 300)] {
+            testing_env!(context.build());
+            contract.ft_on_transfer(sender.clone(), U128(amount), msg.to_string());
+        }
+
+        let transfer = contract.get_bridge_transfer(1).expect("Transfer should exist");
+        assert_eq!(transfer.sender, bob);
+        assert_eq!(transfer.status, BridgeTransferStatus::Pending);
+        assert_eq!(transfer.created_at, env::block_timestamp_ms());
+        assert!(contract.get_bridge_transfer(3).is_none());
+
+        let alice_transfers = contract.get_bridge_transfers_by_sender(alice.clone(), None, None);
+        assert_eq!(alice_transfers.iter().map(|t| t.id).collect::<Vec<_>>(), vec![0, 2]);
+        let page = contract.get_bridge_transfers_by_sender(alice, Some(1), Some(1));
+        assert_eq!(page.iter().map(|t| t.amount).collect::<Vec<_>>(), vec![U128(300)]);
+
+        let pending = contract.get_bridge_transfers_by_status(BridgeTransferStatus::Pending, Some(1), Some(10));
+        assert_eq!(pending.len(), 2);
+
+        // The status index follows every transition
+        testing_env_with_result(setup_context("ft-wrapper.testnet".parse().unwrap()).build(), PromiseResult::Failed);
+        contract.handle_bridge_relay(1);
+        let ids = |contract: &FtWrapperContract, status| {
+            let mut ids: Vec<u64> = contract.get_bridge_transfers_by_status(status, None, None).iter().map(|t| t.id).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(ids(&contract, BridgeTransferStatus::Pending), vec![0, 2]);
+        assert_eq!(ids(&contract, BridgeTransferStatus::Refunding), vec![1]);
+        assert!(ids(&contract, BridgeTransferStatus::Failed).is_empty());
+
+        testing_env!(setup_context("relayer.testnet".parse().unwrap()).build());
+        contract.complete_bridge_transfer(2).unwrap();
+        assert_eq!(ids(&contract, BridgeTransferStatus::Pending), vec![0]);
+        assert_eq!(ids(&contract, BridgeTransferStatus::Completed), vec![2]);
+    }
+
+    #[test]
+    fn test_failed_relay_refunds_sender() {
+        let token: AccountId = "token.testnet".parse().unwrap();
+        let mut contract = contract_with_bridge_transfer(&token, 500);
+        let current: AccountId = "ft-wrapper.testnet".parse().unwrap();
+
+        testing_env_with_result(setup_context(current.clone()).build(), PromiseResult::Failed);
+        assert!(!contract.handle_bridge_relay(0));
+        assert_eq!(contract.get_bridge_transfer(0).unwrap().status, BridgeTransferStatus::Refunding);
+        assert!(
+            get_logs().contains(&"EVENT_JSON:{\"standard\":\"nep297\",\"version\":\"1.0.0\",\"event\":\"bridge_transfer_failed\",\"data\":{\"transfer_id\":0}}".to_string()),
+            "Expected bridge_transfer_failed event, got: {:?}", get_logs()
+        );
+
+        testing_env_with_result(setup_context(current).build(), PromiseResult::Successful(vec![]));
+        contract.handle_bridge_refund(0);
+        assert_eq!(contract.get_bridge_transfer(0).unwrap().status, BridgeTransferStatus::Refunded);
+        assert_eq!(contract.get_locked_balance(token), U128(0));
+        assert!(
+            get_logs().contains(&"EVENT_JSON:{\"standard\":\"nep297\",\"version\":\"1.0.0\",\"event\":\"bridge_transfer_refunded\",\"data\":{\"transfer_id\":0,\"token\":\"token.testnet\",\"sender\":\"alice.testnet\",\"amount\":\"500\"}}".to_string()),
+            "Expected bridge_transfer_refunded event, got: {:?}", get_logs()
+        );
+    }
+
+    #[test]
+    fn test_bridge_transfer_relays_typed_actions() {
+        let token: AccountId = "token.testnet".parse().unwrap();
+        let mut contract = contract_with_bridge_transfer(&token, 500);
+
+        let relay_args = near_sdk::test_utils::get_created_receipts().into_iter()
+            .filter(|receipt| receipt.receiver_id.as_str() == "relayer.testnet")
+            .flat_map(|receipt| receipt.actions)
+            .find_map(|action| match action {
+                near_sdk::mock::MockAction::FunctionCallWeight { method_name, args, .. } if method_name == b"relay_meta_transaction" => Some(args),
+                _ => None,
+            })
+            .expect("Expected a relay_meta_transaction call");
+        let relay_args: near_sdk::serde_json::Value = near_sdk::serde_json::from_slice(&relay_args).unwrap();
+        assert_eq!(relay_args["operation"]["BridgeTransfer"]["transfer_id"], 0);
+        assert_eq!(relay_args["sender_id"], "alice.testnet");
+        let action = &relay_args["actions"][0]["BridgeTransfer"];
+        assert_eq!(action["token"], "token.testnet");
+        assert_eq!(action["amount"], "500");
+        assert_eq!(action["destination_chain"], "ethereum");
+
+        // A rejection or a response the wrapper cannot read fails the transfer
+        let current: AccountId = "ft-wrapper.testnet".parse().unwrap();
+        let rejected = br#"{"accepted":false,"reason":"destination paused"}"#.to_vec();
+        testing_env_with_result(setup_context(current.clone()).build(), PromiseResult::Successful(rejected));
+        assert!(!contract.handle_bridge_relay(0));
+        assert_eq!(contract.get_bridge_transfer(0).unwrap().status, BridgeTransferStatus::Refunding);
+        assert!(get_logs().iter().any(|log| log == "Relayer rejected bridge transfer 0: destination paused"));
+
+        testing_env!(setup_context(token.clone()).build());
+        let msg = r#"{"destination_chain":"ethereum","recipient":"0x52908400098527886E0F7030069857D2E4169EE7"}"#;
+        contract.ft_on_transfer("alice.testnet".parse().unwrap(), U128(200), msg.to_string());
+        testing_env_with_result(setup_context(current).build(), PromiseResult::Successful(vec![]));
+        contract.handle_bridge_relay(1);
+        assert_eq!(contract.get_bridge_transfer(1).unwrap().status, BridgeTransferStatus::Refunding);
+    }
+
+    #[test]
+    fn test_refund_bridge_transfer_after_timeout() {
+        let token: AccountId = "token.testnet".parse().unwrap();
+        let mut contract = contract_with_bridge_transfer(&token, 500);
+        let current: AccountId = "ft-wrapper.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+
+        let accepted = br#"{"accepted":true,"reason":null}"#.to_vec();
+        testing_env_with_result(setup_context(current.clone()).build(), PromiseResult::Successful(accepted));
+        assert!(contract.handle_bridge_relay(0));
+        assert_eq!(contract.get_bridge_transfer(0).unwrap().status, BridgeTransferStatus::Relayed);
+
+        testing_env!(setup_context("bob.testnet".parse().unwrap()).build());
+        assert!(matches!(contract.refund_bridge_transfer_internal(0), Err(FtWrapperError::Unauthorized)));
+
+        testing_env!(setup_context(alice.clone()).build());
+        assert!(matches!(contract.refund_bridge_transfer_internal(0), Err(FtWrapperError::RefundTimeoutNotReached)));
+
+        let mut context = setup_context(alice);
+        let timeout_ns = contract.state.bridge_refund_timeout_ms * 1_000_000;
+        testing_env!(context.block_timestamp(1_000_000_000_000 + timeout_ns).build());
+        contract.refund_bridge_transfer(0);
+        assert_eq!(contract.get_bridge_transfer(0).unwrap().status, BridgeTransferStatus::Refunding);
+
+        // A late relay result must not overwrite the refund
+        testing_env_with_result(setup_context(current).build(), PromiseResult::Failed);
+        assert!(!contract.handle_bridge_relay(0));
+        assert_eq!(contract.get_bridge_transfer(0).unwrap().status, BridgeTransferStatus::Refunding);
+    }
+
+    #[test]
+    fn test_complete_bridge_transfer_by_relayer() {
+        let token: AccountId = "token.testnet".parse().unwrap();
+        let mut contract = contract_with_bridge_transfer(&token, 500);
+
+        testing_env!(setup_context("alice.testnet".parse().unwrap()).build());
+        assert!(matches!(contract.complete_bridge_transfer(0), Err(FtWrapperError::Unauthorized)));
+
+        testing_env!(setup_context("relayer.testnet".parse().unwrap()).build());
+        contract.complete_bridge_transfer(0).expect("Relayer should complete the transfer");
+        assert_eq!(contract.get_bridge_transfer(0).unwrap().status, BridgeTransferStatus::Completed);
+        assert_eq!(contract.get_locked_balance(token), U128(500));
+
+        testing_env!(setup_context("alice.testnet".parse().unwrap()).build());
+        assert!(matches!(contract.refund_bridge_transfer_internal(0), Err(FtWrapperError::InvalidTransferStatus)));
+    }
+
+    #[test]
+    fn test_finalize_transfer_unlocks_released_native_tokens() {
+        let token: AccountId = "token.testnet".parse().unwrap();
+        let current: AccountId = "ft-wrapper.testnet".parse().unwrap();
+        let mut contract = contract_with_bridge_transfer(&token, 5_000);
+        testing_env!(setup_context("relayer.testnet".parse().unwrap()).build());
+        contract.complete_bridge_transfer(0).expect("Relayer should complete the transfer");
+
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).prepaid_gas(Gas::from_tgas(300)).build());
+        let signing_key = ed25519_signing_key();
+        contract.add_mpc_public_key(ed25519_public_key(&signing_key)).unwrap();
+        let message = bridge_message(token.clone());
+        let args = signed_finalize_args(&signing_key, &message);
+        contract.finalize_transfer(args.clone());
+        // The release takes its amount out of the lock before it starts
+        assert_eq!(contract.get_locked_balance(token.clone()), U128(4_000));
+
+        // A failed release puts it back
+        let message_id = crate::ft::message_id(&args.message_payload);
+        testing_env_with_result(setup_context(current.clone()).build(), PromiseResult::Failed);
+        contract.handle_finalize_transfer(message_id, message.clone(), U128(0));
+        assert_eq!(contract.get_locked_balance(token.clone()), U128(5_000));
+
+        testing_env!(setup_context(manager.clone()).prepaid_gas(Gas::from_tgas(300)).build());
+        contract.finalize_transfer(args);
+        testing_env_with_result(setup_context(current).build(), PromiseResult::Successful(vec![]));
+        contract.handle_finalize_transfer(message_id, message, U128(0));
+        assert_eq!(contract.get_locked_balance(token.clone()), U128(4_000));
+
+        // Users' deposits are never released to the other chain
+        testing_env!(setup_context(manager).prepaid_gas(Gas::from_tgas(300)).build());
+        let mut too_large = bridge_message(token);
+        too_large.nonce = 2;
+        too_large.amount = U128(4_001);
+        let result = contract.finalize_transfer_internal(signed_finalize_args(&signing_key, &too_large));
+        assert!(matches!(result, Err(FtWrapperError::InsufficientLockedBalance)));
+    }
+
+    fn bridged_token_metadata() -> BridgedTokenMetadata {
+        BridgedTokenMetadata {
+            spec: "ft-1.0.0".to_string(),
+            name: "Wrapped USDC".to_string(),
+            symbol: "USDC".to_string(),
+            icon: None,
+            decimals: 6,
+        }
+    }
+
+    /// Deploys a bridged token for an ethereum asset and resolves the deployment callback.
+    fn deploy_bridged_token(contract: &mut FtWrapperContract, manager: &AccountId) -> AccountId {
+        let mut context = setup_context(manager.clone()).build();
+        context.input = b"\0asm".to_vec();
+        testing_env!(context);
+        contract.set_bridged_token_code().expect("Failed to set code");
+
+        let mut context = setup_context(manager.clone());
+        testing_env!(context.attached_deposit(NearToken::from_near(3)).build());
+        contract
+            .deploy_bridged_token("ethereum".to_string(), "0xa0b8".to_string(), "usdc".to_string(), bridged_token_metadata())
+            .expect("Failed to deploy bridged token");
+
+        let current: AccountId = "ft-wrapper.testnet".parse().unwrap();
+        let token: AccountId = "usdc.ft-wrapper.testnet".parse().unwrap();
+        testing_env_with_result(setup_context(current).build(), PromiseResult::Successful(vec![]));
+        assert!(contract.handle_bridged_token_deployed("ethereum".to_string(), "0xa0b8".to_string(), token.clone()));
+        token
+    }
+
+    #[test]
+    fn test_deploy_bridged_token() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+
+        let mut context = setup_context(manager.clone());
+        testing_env!(context.attached_deposit(NearToken::from_near(3)).build());
+        assert!(matches!(
+            contract.deploy_bridged_token("ethereum".to_string(), "0xa0b8".to_string(), "usdc".to_string(), bridged_token_metadata()),
+            Err(FtWrapperError::BridgedTokenCodeMissing)
+        ));
+
+        let token = deploy_bridged_token(&mut contract, &manager);
+        assert_eq!(contract.get_bridged_token("ethereum".to_string(), "0xa0b8".to_string()), Some(token.clone()));
+        assert_eq!(contract.get_bridged_token_origin(token.clone()).unwrap().source_chain, "ethereum");
+        assert!(contract.get_supported_tokens().contains(&token));
+        assert!(
+            get_logs().contains(&"EVENT_JSON:{\"standard\":\"nep297\",\"version\":\"1.0.0\",\"event\":\"bridged_token_deployed\",\"data\":{\"token\":\"usdc.ft-wrapper.testnet\",\"source_chain\":\"ethereum\",\"address\":\"0xa0b8\"}}".to_string()),
+            "Expected bridged_token_deployed event, got: {:?}", get_logs()
+        );
+
+        let mut context = setup_context(manager.clone());
+        testing_env!(context.attached_deposit(NearToken::from_near(3)).build());
+        assert!(matches!(
+            contract.deploy_bridged_token("ethereum".to_string(), "0xa0b8".to_string(), "usdc2".to_string(), bridged_token_metadata()),
+            Err(FtWrapperError::BridgedTokenExists)
+        ));
+    }
+
+    #[test]
+    fn test_finalize_transfer_mints_bridged_token() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        let signing_key = ed25519_signing_key();
+        contract.add_mpc_public_key(ed25519_public_key(&signing_key)).unwrap();
+        let token = deploy_bridged_token(&mut contract, &manager);
+
+        let mut message = bridge_message(token);
+        message.is_native = false;
+        let mut context = setup_context(manager.clone());
+        testing_env!(context.prepaid_gas(Gas::from_tgas(300)).build());
+        contract.finalize_transfer(signed_finalize_args(&signing_key, &message));
+
+        let calls = created_function_calls();
+        assert!(
+            calls.contains(&("usdc.ft-wrapper.testnet".parse().unwrap(), "mint".to_string())),
+            "Expected a mint call, got: {:?}", calls
+        );
+
+        // The relayer's fee is minted once the recipient has been paid
+        let current: AccountId = "ft-wrapper.testnet".parse().unwrap();
+        let message_id = crate::ft::message_id(&borsh::to_vec(&message).unwrap());
+        testing_env_with_result(setup_context(current).build(), PromiseResult::Successful(vec![]));
+        assert!(contract.handle_finalize_transfer(message_id, message, U128(10)));
+        assert_eq!(created_function_calls(), vec![("usdc.ft-wrapper.testnet".parse().unwrap(), "mint".to_string())]);
+    }
+
+    #[test]
+    #[should_panic(expected = "TokenNotBridged")]
+    fn test_finalize_transfer_rejects_mint_of_unbridged_token() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let mut context = setup_context(manager.clone());
+        testing_env!(context.prepaid_gas(Gas::from_tgas(300)).build());
+
+        let mut contract = new_contract(&manager);
+        let token: AccountId = "token.testnet".parse().unwrap();
+        contract.add_supported_token(token.clone()).unwrap();
+        contract.state.locked_balances.insert(token.clone(), 1_000);
+        let signing_key = ed25519_signing_key();
+        contract.add_mpc_public_key(ed25519_public_key(&signing_key)).unwrap();
+
+        let mut message = bridge_message(token);
+        message.is_native = false;
+        contract.finalize_transfer(signed_finalize_args(&signing_key, &message));
+    }
+
+    #[test]
+    fn test_complete_bridged_token_transfer_burns() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        let token = deploy_bridged_token(&mut contract, &manager);
+
+        testing_env!(setup_context(token.clone()).build());
+        let msg = r#"{"destination_chain":"ethereum","recipient":"0x52908400098527886E0F7030069857D2E4169EE7"}"#;
+        contract.ft_on_transfer("alice.testnet".parse().unwrap(), U128(500), msg.to_string());
+        assert_eq!(contract.get_locked_balance(token.clone()), U128(500));
+
+        testing_env!(setup_context("relayer.testnet".parse().unwrap()).build());
+        contract.complete_bridge_transfer(0).expect("Relayer should complete the transfer");
+        assert!(created_function_calls().contains(&(token.clone(), "burn".to_string())));
+        // Nothing is unlocked until the burn succeeds
+        assert_eq!(contract.get_locked_balance(token.clone()), U128(500));
+        assert_eq!(contract.get_bridge_transfer(0).unwrap().status, BridgeTransferStatus::Completing);
+        assert!(matches!(contract.complete_bridge_transfer(0), Err(FtWrapperError::InvalidTransferStatus)));
+
+        let current: AccountId = "ft-wrapper.testnet".parse().unwrap();
+        testing_env_with_result(setup_context(current.clone()).build(), PromiseResult::Failed);
+        assert!(!contract.handle_bridge_burn(0));
+        assert_eq!(contract.get_locked_balance(token.clone()), U128(500));
+        assert_eq!(contract.get_bridge_transfer(0).unwrap().status, BridgeTransferStatus::Relayed);
+
+        testing_env!(setup_context("relayer.testnet".parse().unwrap()).build());
+        contract.complete_bridge_transfer(0).expect("Relayer should complete the transfer again");
+        testing_env_with_result(setup_context(current).build(), PromiseResult::Successful(vec![]));
+        assert!(contract.handle_bridge_burn(0));
+        assert_eq!(contract.get_locked_balance(token), U128(0));
+        assert_eq!(contract.get_bridge_transfer(0).unwrap().status, BridgeTransferStatus::Completed);
+        assert!(get_logs().iter().any(|log| log.contains("\"event\":\"bridge_transfer_completed\"")));
+    }
+
+    #[test]
+    fn test_refund_rejected_while_burn_in_flight() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let current: AccountId = "ft-wrapper.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        let token = deploy_bridged_token(&mut contract, &manager);
+
+        testing_env!(setup_context(token.clone()).build());
+        let msg = r#"{"destination_chain":"ethereum","recipient":"0x52908400098527886E0F7030069857D2E4169EE7"}"#;
+        contract.ft_on_transfer(alice.clone(), U128(500), msg.to_string());
+        let accepted = br#"{"accepted":true,"reason":null}"#.to_vec();
+        testing_env_with_result(setup_context(current.clone()).build(), PromiseResult::Successful(accepted));
+        assert!(contract.handle_bridge_relay(0));
+
+        // The relayer reports delivery after the refund timeout has passed
+        let after_timeout = 1_000_000_000_000 + contract.state.bridge_refund_timeout_ms * 1_000_000;
+        testing_env!(setup_context("relayer.testnet".parse().unwrap()).block_timestamp(after_timeout).build());
+        contract.complete_bridge_transfer(0).expect("Relayer should complete the transfer");
+
+        // The sender cannot refund tokens that are being burned
+        testing_env!(setup_context(alice.clone()).block_timestamp(after_timeout).build());
+        assert!(matches!(contract.refund_bridge_transfer_internal(0), Err(FtWrapperError::InvalidTransferStatus)));
+
+        testing_env_with_result(setup_context(current.clone()).block_timestamp(after_timeout).build(), PromiseResult::Successful(vec![]));
+        assert!(contract.handle_bridge_burn(0));
+        assert_eq!(contract.get_bridge_transfer(0).unwrap().status, BridgeTransferStatus::Completed);
+        assert_eq!(contract.get_locked_balance(token.clone()), U128(0));
+
+        // A failed burn makes the transfer refundable again
+        testing_env!(setup_context(token.clone()).build());
+        contract.ft_on_transfer(alice.clone(), U128(300), msg.to_string());
+        testing_env!(setup_context("relayer.testnet".parse().unwrap()).block_timestamp(after_timeout).build());
+        contract.complete_bridge_transfer(1).expect("Relayer should complete the transfer");
+        testing_env_with_result(setup_context(current).block_timestamp(after_timeout).build(), PromiseResult::Failed);
+        assert!(!contract.handle_bridge_burn(1));
+        testing_env!(setup_context(alice).block_timestamp(after_timeout).build());
+        contract.refund_bridge_transfer(1);
+        assert_eq!(contract.get_bridge_transfer(1).unwrap().status, BridgeTransferStatus::Refunding);
+    }
+
+    #[test]
+    fn test_chain_registry() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = FtWrapperContract::new(
+            manager.clone(),
+            "relayer.testnet".parse().unwrap(),
+            U128(1_250_000_000_000_000_000_000),
+        );
+
+        contract.add_chain(ethereum_chain()).expect("Failed to add chain");
+        assert!(matches!(contract.add_chain(ethereum_chain()), Err(FtWrapperError::ChainAlreadyExists)));
+
+        let mut invalid = ethereum_chain();
+        invalid.min_amount = U128(10);
+        invalid.max_amount = U128(1);
+        assert!(matches!(contract.update_chain(invalid), Err(FtWrapperError::InvalidChainConfig)));
+
+        let mut updated = ethereum_chain();
+        updated.required_confirmations = 64;
+        contract.update_chain(updated).expect("Failed to update chain");
+        contract.set_chain_enabled("ethereum".to_string(), false).expect("Failed to disable chain");
+        let chain = contract.get_chain("ethereum".to_string()).unwrap();
+        assert_eq!(chain.required_confirmations, 64);
+        assert!(!chain.enabled);
+
+        contract.remove_chain("ethereum".to_string()).expect("Failed to remove chain");
+        assert!(contract.get_chains().is_empty());
+        assert!(matches!(contract.remove_chain("ethereum".to_string()), Err(FtWrapperError::UnknownChain)));
+
+        let logs = get_logs();
+        for event in ["chain_added", "chain_updated", "chain_removed"] {
+            assert!(
+                logs.contains(&format!("EVENT_JSON:{{\"standard\":\"nep297\",\"version\":\"1.0.0\",\"event\":\"{}\",\"data\":{{\"chain_id\":\"ethereum\"}}}}", event)),
+                "Expected {} event, got: {:?}", event, logs
+            );
+        }
+
+        testing_env!(setup_context("alice.testnet".parse().unwrap()).build());
+        assert!(matches!(contract.add_chain(ethereum_chain()), Err(FtWrapperError::Unauthorized)));
+    }
+
+    #[test]
+    fn test_bridge_transfer_checks_destination_chain() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let msg = |chain: &str| format!(r#"{{"destination_chain":"{}","recipient":"0x52908400098527886E0F7030069857D2E4169EE7"}}"#, chain);
+
+        testing_env!(setup_context(token.clone()).build());
+        assert!(matches!(
+            contract.ft_on_transfer_internal(alice.clone(), U128(500), msg("solana")),
+            Err(FtWrapperError::UnknownChain)
+        ));
+        assert!(matches!(
+            contract.ft_on_transfer_internal(alice.clone(), U128(2_000_000), msg("ethereum")),
+            Err(FtWrapperError::AmountTooHigh)
+        ));
+
+        testing_env!(setup_context(manager).build());
+        contract.set_chain_enabled("ethereum".to_string(), false).unwrap();
+        testing_env!(setup_context(token).build());
+        assert!(matches!(
+            contract.ft_on_transfer_internal(alice, U128(500), msg("ethereum")),
+            Err(FtWrapperError::ChainDisabled)
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "UnknownChain")]
+    fn test_finalize_transfer_rejects_unknown_source_chain() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        let token: AccountId = "token.testnet".parse().unwrap();
+        contract.add_supported_token(token.clone()).unwrap();
+        contract.state.locked_balances.insert(token.clone(), 1_000);
+        let signing_key = ed25519_signing_key();
+        contract.add_mpc_public_key(ed25519_public_key(&signing_key)).unwrap();
+
+        let mut message = bridge_message(token);
+        message.source_chain = "dogecoin".to_string();
+        contract.finalize_transfer(signed_finalize_args(&signing_key, &message));
+    }
+
+    #[test]
+    fn test_validate_recipient_addresses() {
+        testing_env!(setup_context("alice.testnet".parse().unwrap()).build());
+        use crate::address::validate_address;
+
+        let valid = [
+            (AddressFormat::Evm, "0x52908400098527886E0F7030069857D2E4169EE7"),
+            (AddressFormat::Evm, "0xde709f2102306220921060314715629080e2fb77"),
+            (AddressFormat::Bitcoin, "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"),
+            (AddressFormat::Bitcoin, "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0"),
+            (AddressFormat::Bitcoin, "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2"),
+            (AddressFormat::Bitcoin, "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy"),
+            (AddressFormat::BitcoinTestnet, "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx"),
+            (AddressFormat::Solana, "So11111111111111111111111111111111111111112"),
+        ];
+        for (format, address) in valid {
+            assert!(validate_address(format, address).is_ok(), "{} should be a valid {:?} address", address, format);
+        }
+
+        let invalid = [
+            (AddressFormat::Evm, "52908400098527886E0F7030069857D2E4169EE7"),
+            (AddressFormat::Evm, "0x52908400098527886E0F7030069857D2E4169Ee7"),
+            (AddressFormat::Evm, "0x52908400098527886e0f7030069857d2e4169e"),
+            (AddressFormat::Bitcoin, "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5"),
+            (AddressFormat::Bitcoin, "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN3"),
+            (AddressFormat::Bitcoin, "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx"),
+            (AddressFormat::BitcoinTestnet, "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2"),
+            (AddressFormat::Solana, "0x52908400098527886E0F7030069857D2E4169EE7"),
+            (AddressFormat::Solana, "1111111111111111111111111111111"),
+        ];
+        for (format, address) in invalid {
+            assert!(
+                matches!(validate_address(format, address), Err(FtWrapperError::InvalidRecipient(_))),
+                "{} should be an invalid {:?} address", address, format
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "EIP-55")]
+    fn test_bridge_transfer_rejects_bad_checksum() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+
+        testing_env!(setup_context(token).build());
+        let msg = r#"{"destination_chain":"ethereum","recipient":"0x52908400098527886E0F7030069857D2E4169Ee7"}"#;
+        contract.ft_on_transfer("alice.testnet".parse().unwrap(), U128(500), msg.to_string());
+    }
+
+    fn signature_args(token: &AccountId, payload: Vec<u8>) -> RequestChainSignatureArgs {
+        RequestChainSignatureArgs {
+            token: token.clone(),
+            target_chain: "ethereum".to_string(),
+            derivation_path: "ethereum-1".to_string(),
+            payload,
+            transaction: None,
+        }
+    }
+
+    #[test]
+    fn test_request_chain_signature_calls_mpc_signer() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+
+        testing_env!(setup_context("alice.testnet".parse().unwrap())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.request_chain_signature(signature_args(&token, vec![3u8; 32]));
+
+        let receipts = near_sdk::test_utils::get_created_receipts();
+        let sign_call = receipts.iter()
+            .find(|receipt| receipt.receiver_id.as_str() == "v1.signer")
+            .and_then(|receipt| receipt.actions.iter().find_map(|action| match action {
+                near_sdk::mock::MockAction::FunctionCallWeight { method_name, args, attached_deposit, .. } => {
+                    Some((method_name.clone(), args.clone(), *attached_deposit))
+                }
+                _ => None,
+            }))
+            .unwrap();
+        assert_eq!(sign_call.0, b"sign".to_vec());
+        assert_eq!(sign_call.2, NearToken::from_yoctonear(1));
+        let sign_args: near_sdk::serde_json::Value = near_sdk::serde_json::from_slice(&sign_call.1).unwrap();
+        assert_eq!(sign_args["request"]["path"], "alice.testnet,ethereum-1");
+        assert_eq!(sign_args["request"]["key_version"], 0);
+        assert_eq!(sign_args["request"]["payload"].as_array().unwrap().len(), 32);
+        assert!(created_function_calls().iter().any(|(_, method)| method == "handle_signature_result"));
+
+        let request = contract.get_signature_request(0).unwrap();
+        assert_eq!(request.requester.as_str(), "alice.testnet");
+        assert!(request.signature.is_none());
+    }
+
+    #[test]
+    fn test_request_chain_signature_validates_args() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+
+        testing_env!(setup_context("alice.testnet".parse().unwrap())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        let result = contract.request_chain_signature_internal(signature_args(&token, vec![3u8; 31]));
+        assert!(matches!(result, Err(FtWrapperError::InvalidSignaturePayload)));
+        let mut args = signature_args(&token, vec![3u8; 32]);
+        args.target_chain = "dogecoin".to_string();
+        let result = contract.request_chain_signature_internal(args);
+        assert!(matches!(result, Err(FtWrapperError::UnknownChain)));
+
+        // Solana keys are ed25519, so they cannot come from the secp256k1 signer
+        contract.state.chains.insert("solana".to_string(), ChainConfig {
+            chain_id: "solana".to_string(),
+            address_format: AddressFormat::Solana,
+            ..ethereum_chain()
+        });
+        let mut args = signature_args(&token, vec![3u8; 32]);
+        args.target_chain = "solana".to_string();
+        let result = contract.request_chain_signature_internal(args);
+        assert!(matches!(result, Err(FtWrapperError::UnsupportedTransaction)));
+
+        testing_env!(setup_context("alice.testnet".parse().unwrap()).build());
+        let result = contract.request_chain_signature_internal(signature_args(&token, vec![3u8; 32]));
+        assert!(matches!(result, Err(FtWrapperError::InvalidDeposit)));
+        assert!(contract.get_signature_request(0).is_none());
+    }
+
+    #[test]
+    fn test_handle_signature_result_stores_signature() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        testing_env!(setup_context(alice.clone())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.request_chain_signature(signature_args(&token, vec![3u8; 32]));
+        testing_env!(setup_context(alice.clone())
+            .attached_deposit(NearToken::from_yoctonear(5))
+            .build());
+        contract.request_chain_signature(signature_args(&token, vec![4u8; 32]));
+        assert_eq!(contract.get_signature_request(1).unwrap().deposit, U128(5));
+
+        let response = br#"{"big_r":{"affine_point":"02AB"},"s":{"scalar":"CD"},"recovery_id":1}"#.to_vec();
+        let context = setup_context("ft-wrapper.testnet".parse().unwrap()).build();
+        testing_env_with_result(context, PromiseResult::Successful(response));
+        let signed = contract.handle_signature_result(0, 1).unwrap();
+        let signature = signed.signature.unwrap();
+        assert_eq!(signature.big_r.affine_point, "02AB");
+        assert_eq!(signature.recovery_id, 1);
+        let stored = contract.get_signature_request(0).unwrap();
+        assert_eq!(stored.status, SignatureRequestStatus::Signed);
+        assert_eq!(stored.signature.unwrap().s.scalar, "CD");
+        assert!(created_transfers(&alice).is_empty());
+
+        let context = setup_context("ft-wrapper.testnet".parse().unwrap()).build();
+        testing_env_with_result(context, PromiseResult::Failed);
+        assert!(contract.handle_signature_result(1, 1).is_none());
+        let failed = contract.get_signature_request(1).unwrap();
+        assert_eq!(failed.status, SignatureRequestStatus::Failed);
+        assert!(failed.signature.is_none());
+        // The fee the signer gave back goes to the requester, so a retry does not pay twice
+        assert_eq!(created_transfers(&alice), vec![5]);
+    }
+
+    #[test]
+    fn test_retry_signature_request() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+        testing_env!(setup_context(alice.clone())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.request_chain_signature(signature_args(&token, vec![3u8; 32]));
+        contract.request_chain_signature(signature_args(&token, vec![4u8; 32]));
+        assert!(get_logs().iter().any(|log| log.contains("\"event\":\"signature_requested\"")));
+
+        // Still pending: nothing to retry
+        let result = contract.retry_signature_request_internal(0);
+        assert!(matches!(result, Err(FtWrapperError::InvalidSignatureRequestStatus)));
+
+        // The signer never answered the first request
+        let timeout = contract.state.signature_request_timeout_ms;
+        testing_env!(setup_context(alice.clone())
+            .block_timestamp((1_000_000 + timeout) * 1_000_000)
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        assert_eq!(contract.get_signature_request(0).unwrap().status, SignatureRequestStatus::Expired);
+        // Its sign call may still be in flight, so it cannot be retried until the callback ran
+        let result = contract.retry_signature_request_internal(0);
+        assert!(matches!(result, Err(FtWrapperError::InvalidSignatureRequestStatus)));
+        let context = setup_context("ft-wrapper.testnet".parse().unwrap())
+            .block_timestamp((1_000_000 + timeout) * 1_000_000)
+            .build();
+        testing_env_with_result(context, PromiseResult::Failed);
+        contract.handle_signature_result(0, 1);
+        assert_eq!(contract.get_signature_request(0).unwrap().status, SignatureRequestStatus::Failed);
+
+        testing_env!(setup_context("bob.testnet".parse().unwrap())
+            .block_timestamp((1_000_000 + timeout) * 1_000_000)
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        let result = contract.retry_signature_request_internal(0);
+        assert!(matches!(result, Err(FtWrapperError::Unauthorized)));
+        testing_env!(setup_context(alice.clone())
+            .block_timestamp((1_000_000 + timeout) * 1_000_000)
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.retry_signature_request(0);
+        let retried = contract.get_signature_request(0).unwrap();
+        assert_eq!(retried.status, SignatureRequestStatus::Pending);
+        assert_eq!(retried.attempts, 2);
+
+        // Another answer to the first attempt is ignored
+        let response = br#"{"big_r":{"affine_point":"02AB"},"s":{"scalar":"CD"},"recovery_id":0}"#.to_vec();
+        let context = setup_context("ft-wrapper.testnet".parse().unwrap()).build();
+        testing_env_with_result(context, PromiseResult::Successful(response.clone()));
+        contract.handle_signature_result(0, 1);
+        assert_eq!(contract.get_signature_request(0).unwrap().status, SignatureRequestStatus::Pending);
+
+        let context = setup_context("ft-wrapper.testnet".parse().unwrap()).build();
+        testing_env_with_result(context, PromiseResult::Successful(response));
+        contract.handle_signature_result(0, 2);
+        assert!(get_logs().iter().any(|log| log.contains("\"event\":\"signature_issued\"")));
+
+        let listed = contract.get_signature_requests_by_account(alice, None, None);
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].status, SignatureRequestStatus::Signed);
+        assert_eq!(listed[1].status, SignatureRequestStatus::Pending);
+        assert!(contract.get_signature_requests_by_account("bob.testnet".parse().unwrap(), None, None).is_empty());
+    }
+
+    #[test]
+    fn test_derived_address() {
+        use k256::elliptic_curve::ops::Reduce;
+        use sha3::Digest;
+
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        for (chain_id, address_format) in [("bitcoin", AddressFormat::Bitcoin), ("solana", AddressFormat::Solana)] {
+            contract.add_chain(ChainConfig {
+                chain_id: chain_id.to_string(),
+                address_format,
+                ..ethereum_chain()
+            }).unwrap();
+        }
+
+        let result = contract.derived_address(alice.clone(), "ethereum-1".to_string(), "ethereum".to_string());
+        assert!(matches!(result, Err(FtWrapperError::MpcRootKeyMissing)));
+
+        let root_secret = k256::Scalar::from(42u64);
+        let root_secp = k256::ecdsa::SigningKey::from_bytes(&root_secret.to_bytes()).unwrap();
+        let root_secp_bytes = root_secp.verifying_key().to_encoded_point(false).as_bytes()[1..].to_vec();
+        contract.set_mpc_root_public_key(PublicKey::from_parts(CurveType::SECP256K1, root_secp_bytes).unwrap()).unwrap();
+        let root_ed = curve25519_dalek::EdwardsPoint::mul_base(&curve25519_dalek::Scalar::from(42u64)).compress().to_bytes();
+        contract.set_mpc_root_public_key(PublicKey::from_parts(CurveType::ED25519, root_ed.to_vec()).unwrap()).unwrap();
+
+        // The signer tweaks its root key with the wrapper as predecessor and the namespaced path
+        let epsilon: [u8; 32] = sha3::Sha3_256::digest(
+            b"near-mpc-recovery v0.1.0 epsilon derivation:ft-wrapper.testnet,alice.testnet,ethereum-1",
+        ).into();
+        let derived_secret = root_secret + <k256::Scalar as Reduce<k256::U256>>::reduce_bytes(&epsilon.into());
+        let derived_key = k256::ecdsa::SigningKey::from_bytes(&derived_secret.to_bytes()).unwrap();
+        let uncompressed = derived_key.verifying_key().to_encoded_point(false);
+        let hash = env::keccak256_array(&uncompressed.as_bytes()[1..]);
+        let expected: String = hash[12..].iter().map(|b| format!("{:02x}", b)).collect();
+
+        let evm = contract.derived_address(alice.clone(), "ethereum-1".to_string(), "ethereum".to_string()).unwrap();
+        assert_eq!(evm.to_ascii_lowercase(), format!("0x{}", expected));
+        assert_ne!(evm[2..].to_ascii_lowercase(), evm[2..]);
+
+        let btc = contract.derived_address(alice.clone(), "ethereum-1".to_string(), "bitcoin".to_string()).unwrap();
+        let (hrp, version, program) = bech32::segwit::decode(&btc).unwrap();
+        assert_eq!(hrp, bech32::hrp::BC);
+        assert_eq!(version, bech32::Fe32::Q);
+        let compressed = derived_key.verifying_key().to_encoded_point(true);
+        assert_eq!(program, env::ripemd160_array(&env::sha256_array(compressed.as_bytes())).to_vec());
+
+        // The wrapper cannot sign for ed25519 chains yet, so it hands out no address there
+        let result = contract.derived_address(alice.clone(), "ethereum-1".to_string(), "solana".to_string());
+        assert!(matches!(result, Err(FtWrapperError::UnsupportedTransaction)));
+
+        // Other paths and accounts get other keys
+        let other_path = contract.derived_address(alice, "ethereum-2".to_string(), "ethereum".to_string()).unwrap();
+        let other_account = contract.derived_address("bob.testnet".parse().unwrap(), "ethereum-1".to_string(), "ethereum".to_string()).unwrap();
+        assert_ne!(other_path, evm);
+        assert_ne!(other_account, evm);
+
+        let result = contract.derived_address("bob.testnet".parse().unwrap(), "x".to_string(), "dogecoin".to_string());
+        assert!(matches!(result, Err(FtWrapperError::UnknownChain)));
+    }
+
+    fn hex_bytes(hex: &str) -> Vec<u8> {
+        (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_request_chain_signature_for_evm_transaction() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+        contract.add_chain(ChainConfig {
+            chain_id: "bitcoin".to_string(),
+            address_format: AddressFormat::Bitcoin,
+            ..ethereum_chain()
+        }).unwrap();
+
+        testing_env!(setup_context("alice.testnet".parse().unwrap())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .prepaid_gas(Gas::from_tgas(300))
+            .build());
+        let transaction = EvmTransaction {
+            chain_id: 1,
+            nonce: 0,
+            max_priority_fee_per_gas: U128(1_000_000_000),
+            max_fee_per_gas: U128(2_000_000_000),
+            gas_limit: 21_000,
+            to: "0x52908400098527886E0F7030069857D2E4169EE7".to_string(),
+            value: U128(1_000_000_000_000_000_000),
+            data: vec![],
+        };
+        let mut args = signature_args(&token, vec![]);
+        args.transaction = Some(UnsignedTransaction::Evm(transaction));
+        contract.request_chain_signature(args);
+
+        let expected = hex_bytes(concat!(
+            "02ef0180843b9aca00847735940082520894",
+            "52908400098527886e0f7030069857d2e4169ee7",
+            "880de0b6b3a764000080c0",
+        ));
+        let request = contract.get_signature_request(0).unwrap();
+        assert_eq!(request.unsigned_transaction.unwrap(), expected);
+        assert_eq!(request.payload, env::keccak256_array(&expected).to_vec());
+
+        // ERC-20 calldata is longer than 55 bytes, so it takes the long string prefix
+        let transfer = Erc20Transfer {
+            chain_id: 1,
+            nonce: 7,
+            max_priority_fee_per_gas: U128(1_000_000_000),
+            max_fee_per_gas: U128(2_000_000_000),
+            gas_limit: 60_000,
+            token: "0xdac17f958d2ee523a2206206994597c13d831ec7".to_string(),
+            recipient: "0x52908400098527886E0F7030069857D2E4169EE7".to_string(),
+            amount: U128(1_000_000),
+        };
+        let mut args = signature_args(&token, vec![]);
+        args.transaction = Some(UnsignedTransaction::Erc20Transfer(transfer));
+        contract.request_chain_signature(args);
+        let expected_data = hex_bytes(concat!(
+            "a9059cbb",
+            "00000000000000000000000052908400098527886e0f7030069857d2e4169ee7",
+            "00000000000000000000000000000000000000000000000000000000000f4240",
+        ));
+        let unsigned = contract.get_signature_request(1).unwrap().unsigned_transaction.unwrap();
+        let data_start = unsigned.len() - expected_data.len() - 1;
+        assert_eq!(unsigned[data_start - 2..data_start], [0xb8, 0x44]);
+        assert_eq!(unsigned[data_start..unsigned.len() - 1], expected_data[..]);
+        assert_eq!(unsigned[unsigned.len() - 1], 0xc0);
+
+        let mut args = signature_args(&token, vec![3u8; 32]);
+        args.transaction = Some(UnsignedTransaction::Evm(EvmTransaction {
+            chain_id: 1,
+            nonce: 0,
+            max_priority_fee_per_gas: U128(0),
+            max_fee_per_gas: U128(0),
+            gas_limit: 21_000,
+            to: "0x52908400098527886E0F7030069857D2E4169EE7".to_string(),
+            value: U128(0),
+            data: vec![],
+        }));
+        let result = contract.request_chain_signature_internal(args.clone());
+        assert!(matches!(result, Err(FtWrapperError::InvalidSignaturePayload)));
+        args.payload = vec![];
+        args.target_chain = "bitcoin".to_string();
+        let result = contract.request_chain_signature_internal(args);
+        assert!(matches!(result, Err(FtWrapperError::UnsupportedTransaction)));
+    }
+
+    /// Base58check P2PKH address of a 20-byte key hash on mainnet.
+    fn p2pkh_address(hash: &[u8]) -> String {
+        let payload = [&[0x00][..], hash].concat();
+        let checksum = env::sha256_array(&env::sha256_array(&payload));
+        near_sdk::bs58::encode([&payload[..], &checksum[..4]].concat()).into_string()
+    }
+
+    /// Native P2WPKH example from BIP-143, with both inputs spent by the same key type.
+    fn bip143_transaction() -> BitcoinTransaction {
+        let txid = |internal: &str| {
+            let mut bytes = hex_bytes(internal);
+            bytes.reverse();
+            bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>()
+        };
+        BitcoinTransaction {
+            version: 1,
+            lock_time: 17,
+            inputs: vec![
+                BitcoinInput {
+                    txid: txid("fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f"),
+                    vout: 0,
+                    value: U64(625_000_000),
+                    script_type: BitcoinScriptType::P2wpkh,
+                    sequence: Some(0xffff_ffee),
+                },
+                BitcoinInput {
+                    txid: txid("ef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a"),
+                    vout: 1,
+                    value: U64(600_000_000),
+                    script_type: BitcoinScriptType::P2wpkh,
+                    sequence: None,
+                },
+            ],
+            outputs: vec![
+                BitcoinOutput {
+                    address: p2pkh_address(&hex_bytes("8280b37df378db99f66f85c95a783a76ac7a6d59")),
+                    value: U64(112_340_000),
+                },
+                BitcoinOutput {
+                    address: p2pkh_address(&hex_bytes("3bde42dbee7e4dbe6a21b2d50ce2f0167faa8159")),
+                    value: U64(223_450_000),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_bitcoin_sighash_matches_bip143() {
+        testing_env!(setup_context("alice.testnet".parse().unwrap()).build());
+        let tx = bip143_transaction();
+        let unsigned = crate::bitcoin::serialize_unsigned(AddressFormat::Bitcoin, &tx).unwrap();
+        assert_eq!(unsigned, hex_bytes(concat!(
+            "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffff",
+            "ef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206",
+            "000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42db",
+            "ee7e4dbe6a21b2d50ce2f0167faa815988ac11000000",
+        )));
+        let public_key = hex_bytes("025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee6357");
+        let sighashes = crate::bitcoin::segwit_v0_sighashes(&tx, AddressFormat::Bitcoin, &public_key).unwrap();
+        assert_eq!(sighashes[1].to_vec(), hex_bytes("c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670"));
+    }
+
+    #[test]
+    fn test_request_chain_signature_for_bitcoin_spend() {
+        use k256::elliptic_curve::ops::Reduce;
+        use k256::ecdsa::signature::hazmat::PrehashSigner;
+        use sha3::Digest;
+
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+        contract.add_chain(ChainConfig {
+            chain_id: "bitcoin".to_string(),
+            address_format: AddressFormat::Bitcoin,
+            ..ethereum_chain()
+        }).unwrap();
+        let root_secret = k256::Scalar::from(42u64);
+        let root_key = k256::ecdsa::SigningKey::from_bytes(&root_secret.to_bytes()).unwrap();
+        let root_bytes = root_key.verifying_key().to_encoded_point(false).as_bytes()[1..].to_vec();
+        contract.set_mpc_root_public_key(PublicKey::from_parts(CurveType::SECP256K1, root_bytes).unwrap()).unwrap();
+
+        testing_env!(setup_context(alice.clone())
+            .attached_deposit(NearToken::from_yoctonear(3))
+            .prepaid_gas(Gas::from_tgas(300))
+            .build());
+        let mut args = signature_args(&token, vec![]);
+        args.target_chain = "bitcoin".to_string();
+        args.derivation_path = "bitcoin-1".to_string();
+        args.transaction = Some(UnsignedTransaction::Bitcoin(bip143_transaction()));
+        contract.request_chain_signature(args);
+        let sign_calls = created_function_calls().iter().filter(|(_, method)| method == "sign").count();
+        assert_eq!(sign_calls, 2);
+
+        let spend = contract.get_bitcoin_spend(0).unwrap();
+        assert_eq!(spend.signature_request_ids, vec![0, 1]);
+        assert!(spend.signed_transaction.is_none());
+
+        // Sign both sighashes with the derived key, as the MPC signer would
+        let epsilon: [u8; 32] = sha3::Sha3_256::digest(
+            b"near-mpc-recovery v0.1.0 epsilon derivation:ft-wrapper.testnet,alice.testnet,bitcoin-1",
+        ).into();
+        let derived_secret = root_secret + <k256::Scalar as Reduce<k256::U256>>::reduce_bytes(&epsilon.into());
+        let derived_key = k256::ecdsa::SigningKey::from_bytes(&derived_secret.to_bytes()).unwrap();
+        assert_eq!(spend.public_key, derived_key.verifying_key().to_encoded_point(true).as_bytes().to_vec());
+        let mut witness_signatures = Vec::new();
+        for request_id in [1u64, 0] {
+            let sighash = contract.get_signature_request(request_id).unwrap().payload;
+            let (signature, recovery_id): (k256::ecdsa::Signature, _) = derived_key.sign_prehash(&sighash).unwrap();
+            let (r, s) = signature.split_bytes();
+            // Hand back the high-s form; the witness must carry the low-s one
+            let high_s = -*signature.s();
+            let response = format!(
+                r#"{{"big_r":{{"affine_point":"02{}"}},"s":{{"scalar":"{}"}},"recovery_id":{}}}"#,
+                r.iter().map(|b| format!("{:02X}", b)).collect::<String>(),
+                high_s.to_bytes().iter().map(|b| format!("{:02X}", b)).collect::<String>(),
+                recovery_id.to_byte(),
+            );
+            let mut der = k256::ecdsa::Signature::from_scalars(r, s).unwrap().to_der().as_bytes().to_vec();
+            der.push(0x01);
+            witness_signatures.push((request_id, der));
+
+            let context = setup_context("ft-wrapper.testnet".parse().unwrap()).build();
+            testing_env_with_result(context, PromiseResult::Successful(response.into_bytes()));
+            contract.handle_signature_result(request_id, 1);
+        }
+        assert!(get_logs().iter().any(|log| log.contains("\"event\":\"bitcoin_transaction_signed\"")));
+
+        let signed = contract.get_bitcoin_spend(0).unwrap().signed_transaction.unwrap();
+        assert_eq!(signed[..6], [0x01, 0x00, 0x00, 0x00, 0x00, 0x01]);
+        assert_eq!(signed[6..signed.len() - 4], spend.unsigned_transaction[4..spend.unsigned_transaction.len() - 4]
+            .iter()
+            .copied()
+            .chain(witness_signatures.iter().rev().flat_map(|(_, der)| {
+                [vec![0x02, der.len() as u8], der.clone(), vec![0x21], spend.public_key.clone()].concat()
+            }))
+            .collect::<Vec<u8>>()[..]);
+        assert_eq!(signed[signed.len() - 4..], 17u32.to_le_bytes());
+    }
+
+    #[test]
+    fn test_bitcoin_spend_inputs_limited_by_gas() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+        contract.add_chain(ChainConfig {
+            chain_id: "bitcoin".to_string(),
+            address_format: AddressFormat::Bitcoin,
+            ..ethereum_chain()
+        }).unwrap();
+        let root_key = k256::ecdsa::SigningKey::from_bytes(&k256::Scalar::from(42u64).to_bytes()).unwrap();
+        let root_bytes = root_key.verifying_key().to_encoded_point(false).as_bytes()[1..].to_vec();
+        contract.set_mpc_root_public_key(PublicKey::from_parts(CurveType::SECP256K1, root_bytes).unwrap()).unwrap();
+
+        // Three sign calls and their callbacks need 340 Tgas
+        testing_env!(setup_context(alice)
+            .attached_deposit(NearToken::from_yoctonear(3))
+            .prepaid_gas(Gas::from_tgas(300))
+            .build());
+        let mut tx = bip143_transaction();
+        tx.inputs.push(BitcoinInput { vout: 2, ..tx.inputs[1].clone() });
+        let mut args = signature_args(&token, vec![]);
+        args.target_chain = "bitcoin".to_string();
+        args.derivation_path = "bitcoin-1".to_string();
+        args.transaction = Some(UnsignedTransaction::Bitcoin(tx));
+        let result = contract.request_chain_signature_internal(args);
+        assert!(matches!(result, Err(FtWrapperError::InsufficientGas)));
+        assert!(contract.get_bitcoin_spend(0).is_none());
+        assert!(created_function_calls().is_empty());
+    }
+
+    fn deposit_tokens(contract: &mut FtWrapperContract, token: &AccountId, account_id: &AccountId, amount: u128) {
+        testing_env!(setup_context(token.clone()).build());
+        contract.ft_on_transfer(account_id.clone(), U128(amount), String::new());
+    }
+
+    fn action_outcomes(result: PromiseOrValue<Vec<ActionOutcome>>) -> Vec<ActionOutcome> {
+        match result {
+            PromiseOrValue::Value(outcomes) => outcomes,
+            PromiseOrValue::Promise(_) => panic!("Expected the batch to be finished"),
+        }
+    }
+
+    #[test]
+    fn test_execute_actions_checks_permissions() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+
+        testing_env!(setup_context("alice.testnet".parse().unwrap()).build());
+        let result = contract.execute_actions_internal(vec![Action::Transfer { deposit: NearToken::from_near(1) }]);
+        assert!(matches!(result, Err(FtWrapperError::Unauthorized)));
+        let sign = Action::ChainSignatureRequest {
+            target_chain: "ethereum".to_string(),
+            derivation_path: "ethereum-1".to_string(),
+            payload: vec![3u8; 32],
+        };
+        let result = contract.execute_actions_internal(vec![sign]);
+        assert!(matches!(result, Err(FtWrapperError::InvalidDeposit)));
+
+        // Failing to start the first action ends the batch right away
+        testing_env!(setup_context(manager).build());
+        contract.set_cross_contract_gas(50).unwrap();
+        testing_env!(setup_context("alice.testnet".parse().unwrap()).build());
+        let bridge = Action::BridgeTransfer {
+            token: "token.testnet".parse().unwrap(),
+            amount: U128(10),
+            destination_chain: "ethereum".to_string(),
+            recipient: "0x52908400098527886E0F7030069857D2E4169EE7".to_string(),
+        };
+        let transfer = Action::FtTransfer {
+            token: "token.testnet".parse().unwrap(),
+            receiver_id: "bob.testnet".parse().unwrap(),
+            amount: U128(10),
+            memo: None,
+        };
+        // Spending the ledger needs a deposit, so a function-call key cannot do it
+        let result = contract.execute_actions_internal(vec![bridge.clone(), transfer.clone()]);
+        assert!(matches!(result, Err(FtWrapperError::InvalidDeposit)));
+        testing_env!(setup_context("alice.testnet".parse().unwrap()).attached_deposit(NearToken::from_yoctonear(1)).build());
+        let outcomes = action_outcomes(contract.execute_actions(vec![bridge, transfer]));
+        assert_eq!(outcomes[0].status, ActionStatus::Failed);
+        assert_eq!(outcomes[0].error.as_deref(), Some("Insufficient balance"));
+        assert_eq!(outcomes[1].status, ActionStatus::Skipped);
+        assert!(contract.state.action_batches.get(&0).is_none());
+    }
+
+    #[test]
+    fn test_execute_actions_runs_in_order() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        let current: AccountId = "ft-wrapper.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+        contract.set_cross_contract_gas(50).unwrap();
+        let bob: AccountId = "bob.testnet".parse().unwrap();
+        contract.state.storage_balances.insert((token.clone(), bob.clone()), StorageBalance { total: U128(0), available: U128(0) });
+
+        let transfer = Action::FtTransfer {
+            token: token.clone(),
+            receiver_id: bob,
+            amount: U128(10),
+            memo: None,
+        };
+        let sign = Action::ChainSignatureRequest {
+            target_chain: "ethereum".to_string(),
+            derivation_path: "ethereum-1".to_string(),
+            payload: vec![3u8; 32],
+        };
+        deposit_tokens(&mut contract, &token, &alice, 20);
+        testing_env!(setup_context(alice.clone())
+            .attached_deposit(NearToken::from_yoctonear(5))
+            .prepaid_gas(Gas::from_tgas(300))
+            .build());
+        contract.execute_actions(vec![transfer.clone(), sign, transfer]);
+        assert!(created_function_calls().iter().any(|(receiver, method)| receiver == &token && method == "ft_transfer"));
+        assert!(!created_function_calls().iter().any(|(_, method)| method == "sign"));
+
+        // The signature request only starts once the transfer went through
+        testing_env_with_result(
+            setup_context(current.clone()).prepaid_gas(Gas::from_tgas(300)).build(),
+            PromiseResult::Successful(b"true".to_vec()),
+        );
+        contract.handle_action_result(0, 0);
+        assert!(created_function_calls().iter().any(|(_, method)| method == "sign"));
+        assert_eq!(contract.get_signature_request(0).unwrap().requester, alice);
+        assert_eq!(contract.state.action_batches.get(&0).unwrap().remaining_deposit, U128(4));
+
+        // The signer failed: the last transfer is skipped and the unused deposit refunded
+        testing_env_with_result(setup_context(current).build(), PromiseResult::Successful(b"null".to_vec()));
+        let outcomes = action_outcomes(contract.handle_action_result(0, 1));
+        let statuses: Vec<ActionStatus> = outcomes.iter().map(|outcome| outcome.status).collect();
+        assert_eq!(statuses, vec![ActionStatus::Succeeded, ActionStatus::Failed, ActionStatus::Skipped]);
+        assert_eq!(outcomes[1].error.as_deref(), Some("Signature request failed"));
+        assert!(contract.state.action_batches.get(&0).is_none());
+        assert!(get_logs().iter().any(|log| log.contains("\"event\":\"actions_executed\"")));
+        let refunds: Vec<_> = near_sdk::test_utils::get_created_receipts().into_iter()
+            .filter(|receipt| receipt.receiver_id == alice)
+            .flat_map(|receipt| receipt.actions)
+            .filter(|action| matches!(action, near_sdk::mock::MockAction::Transfer { deposit, .. } if *deposit == NearToken::from_yoctonear(4)))
+            .collect();
+        assert_eq!(refunds.len(), 1);
+    }
+
+    #[test]
+    fn test_execute_actions_stops_after_failed_transfer() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        let current: AccountId = "ft-wrapper.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+        let bob: AccountId = "bob.testnet".parse().unwrap();
+        contract.state.storage_balances.insert((token.clone(), bob.clone()), StorageBalance { total: U128(0), available: U128(0) });
+        deposit_tokens(&mut contract, &token, &alice, 20);
+
+        let transfer = Action::FtTransfer {
+            token: token.clone(),
+            receiver_id: bob,
+            amount: U128(10),
+            memo: None,
+        };
+        testing_env!(setup_context(alice.clone())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .prepaid_gas(Gas::from_tgas(300))
+            .build());
+        contract.execute_actions(vec![transfer.clone(), transfer]);
+
+        // The token rejected the transfer, so handle_ft_transfer credited alice back and returned false
+        testing_env_with_result(setup_context(current.clone()).build(), PromiseResult::Failed);
+        assert!(!contract.handle_ft_transfer(token.clone(), alice.clone(), "bob.testnet".parse().unwrap(), U128(10)));
+        assert_eq!(contract.get_balance(token.clone(), alice.clone()), U128(20));
+
+        testing_env_with_result(setup_context(current).build(), PromiseResult::Successful(b"false".to_vec()));
+        let outcomes = action_outcomes(contract.handle_action_result(0, 0));
+        let statuses: Vec<ActionStatus> = outcomes.iter().map(|outcome| outcome.status).collect();
+        assert_eq!(statuses, vec![ActionStatus::Failed, ActionStatus::Skipped]);
+        assert_eq!(outcomes[0].error.as_deref(), Some("Transfer failed"));
+        assert!(!created_function_calls().iter().any(|(_, method)| method == "ft_transfer"));
+        assert_eq!(contract.get_balance(token, alice), U128(20));
+    }
+
+    #[test]
+    fn test_execute_actions_checks_gas() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+        deposit_tokens(&mut contract, &token, &alice, 20);
+
+        let transfer = Action::FtTransfer {
+            token: token.clone(),
+            receiver_id: "bob.testnet".parse().unwrap(),
+            amount: U128(10),
+            memo: None,
+        };
+        let bridge = Action::BridgeTransfer {
+            token: token.clone(),
+            amount: U128(10),
+            destination_chain: "ethereum".to_string(),
+            recipient: "0x52908400098527886E0F7030069857D2E4169EE7".to_string(),
+        };
+        let context = || setup_context(alice.clone())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .prepaid_gas(Gas::from_tgas(300))
+            .build();
+
+        // Registering bob, transferring and bridging needs more than one transaction can attach
+        testing_env!(context());
+        let result = contract.execute_actions_internal(vec![transfer.clone(), bridge]);
+        assert!(matches!(result, Err(FtWrapperError::InsufficientGas)));
+        assert!(contract.state.action_batches.get(&0).is_none());
+        assert_eq!(contract.get_balance(token.clone(), alice.clone()), U128(20));
+
+        testing_env!(context());
+        contract.execute_actions(vec![transfer]);
+        assert!(created_function_calls().iter().any(|(receiver, method)| receiver == &token && method == "ft_transfer"));
+        assert_eq!(contract.get_balance(token, alice), U128(10));
+    }
+
+    #[test]
+    fn test_cancel_stuck_action_batch() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let current: AccountId = "ft-wrapper.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+
+        let sign = Action::ChainSignatureRequest {
+            target_chain: "ethereum".to_string(),
+            derivation_path: "ethereum-1".to_string(),
+            payload: vec![3u8; 32],
+        };
+        testing_env!(setup_context(alice.clone())
+            .attached_deposit(NearToken::from_yoctonear(5))
+            .prepaid_gas(Gas::from_tgas(300))
+            .build());
+        contract.execute_actions(vec![sign.clone(), sign]);
+
+        // handle_action_result never ran, so the batch is still waiting on the first action
+        testing_env!(setup_context(alice.clone()).build());
+        assert!(matches!(contract.cancel_action_batch(0), Err(FtWrapperError::ActionBatchTimeoutNotReached)));
+        testing_env!(setup_context("bob.testnet".parse().unwrap()).block_timestamp(u64::MAX / 2).build());
+        assert!(matches!(contract.cancel_action_batch(0), Err(FtWrapperError::Unauthorized)));
+
+        let an_hour_later = 1_000_000_000_000 + 60 * 60 * 1_000_000_000;
+        testing_env!(setup_context(alice.clone()).block_timestamp(an_hour_later).build());
+        let outcomes = contract.cancel_action_batch(0).unwrap();
+        let statuses: Vec<ActionStatus> = outcomes.iter().map(|outcome| outcome.status).collect();
+        assert_eq!(statuses, vec![ActionStatus::Failed, ActionStatus::Skipped]);
+        assert_eq!(outcomes[0].error.as_deref(), Some("Action timed out"));
+        assert!(contract.state.action_batches.get(&0).is_none());
+        assert_eq!(created_transfers(&alice), vec![4]);
+
+        // A result arriving after the cancellation is ignored
+        testing_env_with_result(setup_context(current).build(), PromiseResult::Successful(b"null".to_vec()));
+        assert!(action_outcomes(contract.handle_action_result(0, 0)).is_empty());
+        assert!(matches!(contract.cancel_action_batch(0), Err(FtWrapperError::ActionBatchNotFound)));
+    }
+
+    fn signed_delegate(signing_key: &ed25519_dalek::SigningKey, delegate_action: DelegateAction) -> SignedDelegateAction {
+        use ed25519_dalek::Signer;
+        let hash = crate::delegate::delegate_action_hash(&delegate_action);
+        SignedDelegateAction { delegate_action, signature: signing_key.sign(&hash).to_bytes().to_vec() }
+    }
+
+    fn transfer_delegate(signing_key: &ed25519_dalek::SigningKey, token: &AccountId, nonce: u64) -> DelegateAction {
+        DelegateAction {
+            sender_id: "alice.testnet".parse().unwrap(),
+            receiver_id: "ft-wrapper.testnet".parse().unwrap(),
+            actions: vec![Action::FtTransfer {
+                token: token.clone(),
+                receiver_id: "bob.testnet".parse().unwrap(),
+                amount: U128(10),
+                memo: None,
+            }],
+            nonce,
+            max_block_height: 100,
+            public_key: ed25519_public_key(signing_key),
+        }
+    }
+
+    #[test]
+    fn test_submit_signed_delegate_runs_as_sender() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let relayer: AccountId = "relayer.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        let signing_key = ed25519_signing_key();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+
+        testing_env!(setup_context(alice.clone()).build());
+        contract.register_intent_key(ed25519_public_key(&signing_key), None, None).unwrap();
+        assert!(matches!(
+            contract.register_intent_key(ed25519_public_key(&signing_key), None, None),
+            Err(FtWrapperError::IntentKeyAlreadyExists)
+        ));
+        deposit_tokens(&mut contract, &token, &alice, 10);
+
+        testing_env!(setup_context(relayer.clone()).block_height(10).prepaid_gas(Gas::from_tgas(300)).build());
+        contract.submit_signed_delegate(signed_delegate(&signing_key, transfer_delegate(&signing_key, &token, 1)));
+        assert!(created_function_calls().iter().any(|(receiver, method)| receiver == &token && method == "ft_transfer"));
+        assert_eq!(contract.state.action_batches.get(&0).unwrap().sender, alice);
+        assert_eq!(contract.get_intent_nonce(alice.clone()), 1);
+        assert_eq!(contract.get_balance(token, alice), U128(0));
+        assert!(get_logs().iter().any(|log| log.contains("\"event\":\"delegate_action_executed\"")));
+    }
+
+    #[test]
+    fn test_submit_signed_delegate_rejects_invalid_actions() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        let signing_key = ed25519_signing_key();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+        testing_env!(setup_context("alice.testnet".parse().unwrap()).build());
+        contract.register_intent_key(ed25519_public_key(&signing_key), None, None).unwrap();
+        testing_env!(setup_context("relayer.testnet".parse().unwrap()).block_height(10).prepaid_gas(Gas::from_tgas(300)).build());
+
+        // Signed by a key that is not the registered one
+        let other_key = ed25519_dalek::SigningKey::from_bytes(&[9u8; 32]);
+        let forged = signed_delegate(&other_key, transfer_delegate(&signing_key, &token, 1));
+        assert!(matches!(contract.submit_signed_delegate_internal(forged), Err(FtWrapperError::InvalidDelegateSignature)));
+        let unknown = signed_delegate(&other_key, transfer_delegate(&other_key, &token, 1));
+        assert!(matches!(contract.submit_signed_delegate_internal(unknown), Err(FtWrapperError::IntentKeyNotFound)));
+
+        let mut wrong_receiver = transfer_delegate(&signing_key, &token, 1);
+        wrong_receiver.receiver_id = "other.testnet".parse().unwrap();
+        let result = contract.submit_signed_delegate_internal(signed_delegate(&signing_key, wrong_receiver));
+        assert!(matches!(result, Err(FtWrapperError::InvalidDelegateAction)));
+
+        let mut manager_only = transfer_delegate(&signing_key, &token, 1);
+        manager_only.actions = vec![Action::Transfer { deposit: NearToken::from_near(1) }];
+        let result = contract.submit_signed_delegate_internal(signed_delegate(&signing_key, manager_only));
+        assert!(matches!(result, Err(FtWrapperError::ActionNotSupported)));
+
+        let mut expired = transfer_delegate(&signing_key, &token, 1);
+        expired.max_block_height = 9;
+        let result = contract.submit_signed_delegate_internal(signed_delegate(&signing_key, expired));
+        assert!(matches!(result, Err(FtWrapperError::DelegateActionExpired)));
+
+        // A nonce can only be used once
+        let signed = signed_delegate(&signing_key, transfer_delegate(&signing_key, &token, 5));
+        assert!(contract.submit_signed_delegate_internal(signed.clone()).is_ok());
+        assert!(matches!(contract.submit_signed_delegate_internal(signed), Err(FtWrapperError::InvalidNonce)));
+        let older = signed_delegate(&signing_key, transfer_delegate(&signing_key, &token, 4));
+        assert!(matches!(contract.submit_signed_delegate_internal(older), Err(FtWrapperError::InvalidNonce)));
+    }
+
+    #[test]
+    fn test_intent_key_expiry_scopes_and_revocation() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        let signing_key = ed25519_signing_key();
+        let bridge_key = ed25519_dalek::SigningKey::from_bytes(&[9u8; 32]);
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+
+        testing_env!(setup_context(alice.clone()).build());
+        let now = env::block_timestamp_ms();
+        let secp_key: PublicKey = "secp256k1:qMoRgcoXai4mBPsdbHi1wfyxF9TdbPCF4qSDQTRP3TfescSRoUdSx6nmeQoN3aiwGzwMyGXAb1gUjBTv5AY8DXj".parse().unwrap();
+        assert!(matches!(contract.register_intent_key(secp_key, None, None), Err(FtWrapperError::InvalidIntentKey)));
+        assert!(matches!(
+            contract.register_intent_key(ed25519_public_key(&signing_key), Some(now), None),
+            Err(FtWrapperError::InvalidIntentKey)
+        ));
+        assert!(matches!(
+            contract.register_intent_key(ed25519_public_key(&signing_key), None, Some(vec!["withdraw".to_string()])),
+            Err(FtWrapperError::InvalidIntentKey)
+        ));
+        contract.register_intent_key(ed25519_public_key(&signing_key), Some(now + 1_000), None).unwrap();
+        contract.register_intent_key(ed25519_public_key(&bridge_key), None, Some(vec!["bridge_transfer".to_string()])).unwrap();
+        assert_eq!(contract.get_intent_keys(alice.clone()).len(), 2);
+
+        // A key scoped to bridge transfers cannot sign a token transfer
+        testing_env!(setup_context("relayer.testnet".parse().unwrap()).block_height(10).prepaid_gas(Gas::from_tgas(300)).build());
+        let scoped = signed_delegate(&bridge_key, transfer_delegate(&bridge_key, &token, 1));
+        assert!(matches!(contract.submit_signed_delegate_internal(scoped), Err(FtWrapperError::IntentKeyOutOfScope)));
+
+        // Nonces are per account: a second key cannot reuse the first key's nonce
+        let signed = signed_delegate(&signing_key, transfer_delegate(&signing_key, &token, 3));
+        assert!(contract.submit_signed_delegate_internal(signed).is_ok());
+        let mut bridge = transfer_delegate(&bridge_key, &token, 3);
+        bridge.actions = vec![Action::BridgeTransfer {
+            token: token.clone(),
+            amount: U128(10),
+            destination_chain: "ethereum".to_string(),
+            recipient: "0x52908400098527886E0F7030069857D2E4169EE7".to_string(),
+        }];
+        let result = contract.submit_signed_delegate_internal(signed_delegate(&bridge_key, bridge));
+        assert!(matches!(result, Err(FtWrapperError::InvalidNonce)));
+        assert_eq!(contract.get_intent_nonce(alice.clone()), 3);
+
+        // Expired keys are rejected and drop out of the view
+        testing_env!(setup_context("relayer.testnet".parse().unwrap())
+            .block_timestamp((now + 1_000) * 1_000_000)
+            .block_height(10)
+            .build());
+        let signed = signed_delegate(&signing_key, transfer_delegate(&signing_key, &token, 4));
+        assert!(matches!(contract.submit_signed_delegate_internal(signed), Err(FtWrapperError::IntentKeyExpired)));
+        assert_eq!(contract.get_intent_keys(alice.clone()).len(), 1);
+
+        testing_env!(setup_context(alice.clone()).block_timestamp((now + 1_000) * 1_000_000).build());
+        contract.revoke_intent_key(ed25519_public_key(&bridge_key)).unwrap();
+        assert!(matches!(contract.revoke_intent_key(ed25519_public_key(&bridge_key)), Err(FtWrapperError::IntentKeyNotFound)));
+        assert!(contract.get_intent_keys(alice).is_empty());
+        assert!(get_logs().iter().any(|log| log.contains("\"event\":\"intent_key_revoked\"")));
+    }
+
+    #[test]
+    fn test_intent_keys_are_capped() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        let key = |seed: u8| ed25519_public_key(&ed25519_dalek::SigningKey::from_bytes(&[seed; 32]));
+
+        testing_env!(setup_context(alice.clone()).build());
+        let now = env::block_timestamp_ms();
+        contract.register_intent_key(key(0), Some(now + 1_000), None).unwrap();
+        for seed in 1..10 {
+            contract.register_intent_key(key(seed), None, None).unwrap();
+        }
+        assert!(matches!(contract.register_intent_key(key(10), None, None), Err(FtWrapperError::TooManyIntentKeys)));
+
+        // Once a key expires its place can be taken
+        testing_env!(setup_context(alice.clone()).block_timestamp((now + 1_000) * 1_000_000).build());
+        contract.register_intent_key(key(10), None, None).unwrap();
+        assert_eq!(contract.state.intent_keys.get(&alice).unwrap().len(), 10);
+        assert!(matches!(contract.register_intent_key(key(11), None, None), Err(FtWrapperError::TooManyIntentKeys)));
+    }
+
+    #[test]
+    fn test_ledger_backs_transfers() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let bob: AccountId = "bob.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+
+        deposit_tokens(&mut contract, &token, &alice, 100);
+        assert_eq!(contract.get_balance(token.clone(), alice.clone()), U128(100));
+        assert!(get_logs().iter().any(|log| log.contains("\"event\":\"deposited\"")));
+
+        // Nobody can move tokens they did not deposit
+        testing_env!(setup_context(bob.clone()).prepaid_gas(Gas::from_tgas(300)).build());
+        let args = FtTransferArgs { token: token.clone(), receiver_id: bob.clone(), amount: U128(10), memo: None };
+        assert!(matches!(contract.ft_transfer_internal(args), Err(FtWrapperError::InsufficientBalance)));
+
+        testing_env!(setup_context(alice.clone())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .prepaid_gas(Gas::from_tgas(300))
+            .build());
+        let args = FtTransferArgs { token: token.clone(), receiver_id: bob.clone(), amount: U128(60), memo: None };
+        contract.ft_transfer(args);
+        assert_eq!(contract.get_balance(token.clone(), alice.clone()), U128(40));
+        // Only the receiver is registered; the sender's ledger balance needs no registration
+        let registrations: Vec<_> = created_function_calls().into_iter()
+            .filter(|(_, method)| method == "storage_deposit")
+            .collect();
+        assert_eq!(registrations, vec![(token.clone(), "storage_deposit".to_string())]);
+        // Nothing is reported until the token has answered
+        assert!(!get_logs().iter().any(|log| log.contains("\"event\":\"ft_transfer\"")));
+
+        // A failed token transfer credits the sender back
+        testing_env_with_result(setup_context("ft-wrapper.testnet".parse().unwrap()).build(), PromiseResult::Failed);
+        assert!(!contract.handle_ft_transfer(token.clone(), alice.clone(), bob.clone(), U128(60)));
+        assert_eq!(contract.get_balance(token.clone(), alice.clone()), U128(100));
+        assert!(get_logs().iter().any(|log| log.contains("\"event\":\"ft_transfer_failed\"")));
+        assert!(!get_logs().iter().any(|log| log.contains("\"event\":\"ft_transfer\"")));
+
+        testing_env_with_result(setup_context("ft-wrapper.testnet".parse().unwrap()).build(), PromiseResult::Successful(vec![]));
+        assert!(contract.handle_ft_transfer(token.clone(), alice.clone(), bob.clone(), U128(60)));
+        assert!(get_logs().iter().any(|log| log.contains("\"event\":\"ft_transfer\"")));
+
+        // Bridging moves the amount from the balance into the locked total
+        testing_env!(setup_context(alice.clone())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .prepaid_gas(Gas::from_tgas(300))
+            .build());
+        contract.bridge_transfer(BridgeTransferArgs {
+            token: token.clone(),
+            amount: U128(30),
+            destination_chain: "ethereum".to_string(),
+            recipient: "0x52908400098527886E0F7030069857D2E4169EE7".to_string(),
+        });
+        assert_eq!(contract.get_balance(token.clone(), alice.clone()), U128(70));
+        assert_eq!(contract.get_locked_balance(token.clone()), U128(30));
+        assert_eq!(contract.get_bridge_transfer(0).unwrap().sender, alice);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of exactly 1 yoctoNEAR")]
+    fn test_ft_transfer_requires_one_yocto() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+        deposit_tokens(&mut contract, &token, &alice, 100);
+
+        testing_env!(setup_context(alice).prepaid_gas(Gas::from_tgas(300)).build());
+        contract.ft_transfer(FtTransferArgs { token, receiver_id: "bob.testnet".parse().unwrap(), amount: U128(10), memo: None });
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of exactly 1 yoctoNEAR")]
+    fn test_bridge_transfer_requires_one_yocto() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+        deposit_tokens(&mut contract, &token, &alice, 100);
+
+        testing_env!(setup_context(alice).prepaid_gas(Gas::from_tgas(300)).build());
+        contract.bridge_transfer(BridgeTransferArgs {
+            token,
+            amount: U128(10),
+            destination_chain: "ethereum".to_string(),
+            recipient: "0x52908400098527886E0F7030069857D2E4169EE7".to_string(),
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of exactly 1 yoctoNEAR")]
+    fn test_withdraw_requires_one_yocto() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+        deposit_tokens(&mut contract, &token, &alice, 100);
+
+        testing_env!(setup_context(alice).prepaid_gas(Gas::from_tgas(300)).build());
+        contract.withdraw(token, U128(10), None);
+    }
+
+    #[test]
+    fn test_withdraw_rolls_back_on_failure() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let bob: AccountId = "bob.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        let current: AccountId = "ft-wrapper.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+        deposit_tokens(&mut contract, &token, &alice, 100);
+
+        let withdraw_context = || setup_context(alice.clone())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .prepaid_gas(Gas::from_tgas(300))
+            .build();
+        testing_env!(withdraw_context());
+        assert!(matches!(
+            contract.withdraw_internal(token.clone(), U128(101), None),
+            Err(FtWrapperError::InsufficientBalance)
+        ));
+        contract.withdraw(token.clone(), U128(40), Some(bob.clone()));
+        assert!(created_function_calls().iter().any(|(receiver, method)| receiver == &token && method == "ft_transfer"));
+        assert_eq!(contract.get_balance(token.clone(), alice.clone()), U128(60));
+
+        testing_env_with_result(setup_context(current.clone()).build(), PromiseResult::Successful(vec![]));
+        assert!(contract.handle_withdraw(token.clone(), alice.clone(), bob.clone(), U128(40)));
+        assert!(get_logs().iter().any(|log| log.contains("\"event\":\"withdrawn\"") && log.contains("bob.testnet")));
+        assert_eq!(contract.get_balance(token.clone(), alice.clone()), U128(60));
+
+        testing_env!(withdraw_context());
+        contract.withdraw(token.clone(), U128(60), None);
+        assert_eq!(contract.get_balance(token.clone(), alice.clone()), U128(0));
+        testing_env_with_result(setup_context(current).build(), PromiseResult::Failed);
+        assert!(!contract.handle_withdraw(token.clone(), alice.clone(), alice.clone(), U128(60)));
+        assert!(get_logs().iter().any(|log| log.contains("\"event\":\"withdraw_failed\"")));
+        assert_eq!(contract.get_balance(token, alice), U128(60));
+    }
+
+    #[test]
+    fn test_withdraw_after_token_removed() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let bob: AccountId = "bob.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+        deposit_tokens(&mut contract, &token, &alice, 100);
+        testing_env!(setup_context(manager).build());
+        contract.remove_supported_token(token.clone()).unwrap();
+
+        testing_env!(setup_context(alice.clone())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .prepaid_gas(Gas::from_tgas(300))
+            .build());
+        contract.withdraw(token.clone(), U128(40), Some(bob));
+        assert!(created_function_calls().iter().any(|(receiver, method)| receiver == &token && method == "ft_transfer"));
+        assert_eq!(contract.get_balance(token.clone(), alice.clone()), U128(60));
+
+        // Only what is in the ledger can leave it
+        let other: AccountId = "other-token.testnet".parse().unwrap();
+        assert!(matches!(
+            contract.withdraw_internal(other, U128(1), None),
+            Err(FtWrapperError::InsufficientBalance)
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of exactly 1 yoctoNEAR")]
+    fn test_ft_transfer_call_requires_one_yocto() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+        deposit_tokens(&mut contract, &token, &alice, 100);
+
+        testing_env!(setup_context(alice).prepaid_gas(Gas::from_tgas(300)).build());
+        contract.ft_transfer_call(token, "dex.testnet".parse().unwrap(), U128(10), None, "swap".to_string());
+    }
+
+    #[test]
+    fn test_ft_transfer_call_credits_unused_amount() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let dex: AccountId = "dex.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        let current: AccountId = "ft-wrapper.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+        deposit_tokens(&mut contract, &token, &alice, 100);
+
+        let call_context = || setup_context(alice.clone())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .prepaid_gas(Gas::from_tgas(300))
+            .build();
+        testing_env!(call_context());
+        contract.ft_transfer_call(token.clone(), dex.clone(), U128(80), None, "swap".to_string());
+        assert!(created_function_calls().iter().any(|(receiver, method)| receiver == &token && method == "ft_transfer_call"));
+        assert_eq!(contract.get_balance(token.clone(), alice.clone()), U128(20));
+
+        // The receiver used 50 of the 80 tokens
+        testing_env_with_result(setup_context(current.clone()).build(), PromiseResult::Successful(b"\"50\"".to_vec()));
+        assert_eq!(contract.handle_ft_transfer_call(token.clone(), alice.clone(), dex.clone(), U128(80)), U128(50));
+        assert_eq!(contract.get_balance(token.clone(), alice.clone()), U128(50));
+
+        // Nothing moved when the call itself failed
+        testing_env!(call_context());
+        contract.ft_transfer_call(token.clone(), dex.clone(), U128(50), None, "swap".to_string());
+        testing_env_with_result(setup_context(current).build(), PromiseResult::Failed);
+        assert_eq!(contract.handle_ft_transfer_call(token.clone(), alice.clone(), dex, U128(50)), U128(0));
+        assert_eq!(contract.get_balance(token, alice), U128(50));
+    }
+
+    #[test]
+    fn test_registration_follows_storage_deposit_result() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        let current: AccountId = "ft-wrapper.testnet".parse().unwrap();
+        let storage_deposits = || created_function_calls().iter()
+            .filter(|(receiver, method)| receiver == &token && method == "storage_deposit")
+            .count();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+
+        testing_env!(setup_context(alice.clone()).prepaid_gas(Gas::from_tgas(300)).build());
+        contract.storage_deposit(token.clone(), None, None);
+        assert_eq!(contract.get_registration_status(token.clone(), alice.clone()), Some(RegistrationStatus::Pending));
+        assert!(contract.state.storage_balances.get(&(token.clone(), alice.clone())).is_none());
+
+        // A deposit already in flight is not paid again
+        testing_env!(setup_context(alice.clone()).prepaid_gas(Gas::from_tgas(300)).build());
+        assert!(matches!(
+            contract.storage_deposit_internal(token.clone(), None, None),
+            Err(FtWrapperError::RegistrationPending)
+        ));
+        crate::ft::ensure_registered(&mut contract.state, token.clone(), alice.clone()).unwrap();
+        assert_eq!(storage_deposits(), 0);
+
+        testing_env_with_result(setup_context(current.clone()).build(), PromiseResult::Failed);
+        let sponsored = StorageBalance { total: U128(0), available: U128(0) };
+        assert!(!contract.handle_storage_deposit(token.clone(), alice.clone(), U128(1_250), current.clone(), sponsored.clone()));
+        assert_eq!(contract.get_registration_status(token.clone(), alice.clone()), Some(RegistrationStatus::Failed));
+        assert!(get_logs().iter().any(|log| log.contains("\"event\":\"storage_deposit_failed\"")));
+
+        // A failed registration is paid again
+        testing_env!(setup_context(alice.clone()).prepaid_gas(Gas::from_tgas(300)).build());
+        contract.storage_deposit(token.clone(), None, None);
+        assert_eq!(storage_deposits(), 1);
+
+        testing_env_with_result(setup_context(current.clone()).build(), PromiseResult::Successful(vec![]));
+        assert!(contract.handle_storage_deposit(token.clone(), alice.clone(), U128(1_250), current.clone(), sponsored));
+        assert_eq!(contract.get_registration_status(token.clone(), alice.clone()), Some(RegistrationStatus::Registered));
+        assert_eq!(contract.state.storage_balances.get(&(token, alice)).unwrap().total, U128(0));
+    }
+
+    #[test]
+    fn test_reconcile_registrations() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        let accounts: Vec<AccountId> = ["alice.testnet", "bob.testnet", "carol.testnet", "dave.testnet"]
+            .iter()
+            .map(|account| account.parse().unwrap())
+            .collect();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+        for account_id in &accounts[..2] {
+            contract.state.storage_balances.insert(
+                (token.clone(), account_id.clone()),
+                StorageBalance { total: U128(1_550), available: U128(300) },
+            );
+        }
+
+        testing_env!(setup_context(accounts[0].clone()).build());
+        assert!(matches!(
+            contract.reconcile_registrations(token.clone(), accounts.clone()),
+            Err(FtWrapperError::Unauthorized)
+        ));
+        testing_env!(setup_context(manager.clone()).prepaid_gas(Gas::from_tgas(300)).build());
+        assert!(matches!(
+            contract.reconcile_registrations(token.clone(), Vec::new()),
+            Err(FtWrapperError::InvalidReconciliationBatch)
+        ));
+        // A full batch of queries fits in one transaction
+        let full_batch: Vec<AccountId> = (0..10).map(|i| format!("user{}.testnet", i).parse().unwrap()).collect();
+        contract.reconcile_registrations(token.clone(), full_batch).unwrap();
+        testing_env!(setup_context(manager).prepaid_gas(Gas::from_tgas(300)).build());
+        contract.reconcile_registrations(token.clone(), accounts.clone()).unwrap();
+        let queries = created_function_calls().iter()
+            .filter(|(receiver, method)| receiver == &token && method == "storage_balance_of")
+            .count();
+        assert_eq!(queries, 4);
+
+        // alice agrees, bob is gone on the token, carol is registered there, dave's query failed
+        let registered = br#"{"total":"1250","available":"0"}"#.to_vec();
+        testing_env!(
+            setup_context("ft-wrapper.testnet".parse().unwrap()).build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![
+                PromiseResult::Successful(registered.clone()),
+                PromiseResult::Successful(b"null".to_vec()),
+                PromiseResult::Successful(registered),
+                PromiseResult::Failed,
+            ],
+        );
+        contract.handle_reconciliation(token.clone(), accounts.clone());
+        let statuses: Vec<Option<RegistrationStatus>> = accounts.iter()
+            .map(|account_id| contract.get_registration_status(token.clone(), account_id.clone()))
+            .collect();
+        assert_eq!(statuses, vec![Some(RegistrationStatus::Registered), None, Some(RegistrationStatus::Registered), None]);
+        // The token already refunded bob's minimum, so only what the wrapper kept is paid back;
+        // carol is not credited with a balance nobody paid here
+        assert_eq!(created_transfers(&accounts[1]), vec![300]);
+        let carol = contract.state.storage_balances.get(&(token.clone(), accounts[2].clone())).cloned().unwrap();
+        assert_eq!((carol.total, carol.available), (U128(0), U128(0)));
+        let alice = contract.state.storage_balances.get(&(token.clone(), accounts[0].clone())).cloned().unwrap();
+        assert_eq!(alice.total, U128(1_550));
+        let report = get_logs().into_iter().find(|log| log.contains("\"event\":\"registrations_reconciled\"")).unwrap();
+        assert!(report.contains("\"registered\":[\"carol.testnet\"]"));
+        assert!(report.contains("\"unregistered\":[\"bob.testnet\"]"));
+    }
+
+    #[test]
+    fn test_user_paid_storage_deposit() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let bob: AccountId = "bob.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        let current: AccountId = "ft-wrapper.testnet".parse().unwrap();
+        let minimum = 1_250_000_000_000_000_000_000u128;
+        let refunds_to = |account_id: &AccountId| -> Vec<u128> {
+            near_sdk::test_utils::get_created_receipts().into_iter()
+                .filter(|receipt| &receipt.receiver_id == account_id)
+                .flat_map(|receipt| receipt.actions)
+                .filter_map(|action| match action {
+                    near_sdk::mock::MockAction::Transfer { deposit, .. } => Some(deposit.as_yoctonear()),
+                    _ => None,
+                })
+                .collect()
+        };
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+        contract.set_storage_sponsorship(StorageSponsorship::UserPaid).unwrap();
+        assert_eq!(contract.get_storage_sponsorship(), StorageSponsorship::UserPaid);
+
+        // Without sponsorship nothing is paid on the caller's behalf
+        testing_env!(setup_context(alice.clone()).prepaid_gas(Gas::from_tgas(300)).build());
+        assert!(matches!(contract.storage_deposit_internal(token.clone(), None, None), Err(FtWrapperError::InvalidDeposit)));
+        let args = FtTransferArgs { token: token.clone(), receiver_id: bob.clone(), amount: U128(1), memo: None };
+        deposit_tokens(&mut contract, &token, &alice, 1);
+        testing_env!(setup_context(alice.clone()).prepaid_gas(Gas::from_tgas(300)).build());
+        assert!(matches!(contract.ft_transfer_internal(args), Err(FtWrapperError::AccountNotRegistered)));
+
+        testing_env!(setup_context(alice.clone())
+            .attached_deposit(NearToken::from_yoctonear(minimum - 1))
+            .prepaid_gas(Gas::from_tgas(300))
+            .build());
+        assert!(matches!(
+            contract.storage_deposit_internal(token.clone(), None, None),
+            Err(FtWrapperError::InsufficientStorageBalance)
+        ));
+
+        // registration_only refunds everything above the minimum right away
+        testing_env!(setup_context(alice.clone())
+            .attached_deposit(NearToken::from_yoctonear(minimum + 500))
+            .prepaid_gas(Gas::from_tgas(300))
+            .build());
+        let balance = contract.storage_deposit(token.clone(), Some(bob.clone()), Some(true));
+        assert_eq!((balance.total, balance.available), (U128(minimum), U128(0)));
+        assert_eq!(refunds_to(&alice), vec![500]);
+
+        // The excess is kept as available otherwise, and refunded to the payer if the token refuses
+        testing_env!(setup_context(alice.clone())
+            .attached_deposit(NearToken::from_yoctonear(minimum + 500))
+            .prepaid_gas(Gas::from_tgas(300))
+            .build());
+        let balance = contract.storage_deposit(token.clone(), None, None);
+        assert_eq!((balance.total, balance.available), (U128(minimum + 500), U128(500)));
+        testing_env_with_result(setup_context(current.clone()).build(), PromiseResult::Failed);
+        contract.handle_storage_deposit(token.clone(), alice.clone(), U128(minimum), alice.clone(), balance.clone());
+        assert_eq!(refunds_to(&alice), vec![minimum + 500]);
+        testing_env_with_result(setup_context(current).build(), PromiseResult::Successful(vec![]));
+        contract.handle_storage_deposit(token.clone(), alice.clone(), U128(minimum), alice.clone(), balance);
+
+        // Once registered, further deposits top up the balance
+        testing_env!(setup_context(alice.clone()).attached_deposit(NearToken::from_yoctonear(100)).build());
+        let balance = contract.storage_deposit(token.clone(), None, None);
+        assert_eq!((balance.total, balance.available), (U128(minimum + 600), U128(600)));
+        testing_env!(setup_context(alice.clone()).attached_deposit(NearToken::from_yoctonear(100)).build());
+        let balance = contract.storage_deposit(token.clone(), None, Some(true));
+        assert_eq!(balance.available, U128(600));
+        assert_eq!(refunds_to(&alice), vec![100]);
+    }
+
+    #[test]
+    fn test_storage_unregister_refunds_available_only() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let alice: AccountId = "alice.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        let current: AccountId = "ft-wrapper.testnet".parse().unwrap();
+        let minimum = 1_250_000_000_000_000_000_000u128;
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+        contract.set_storage_sponsorship(StorageSponsorship::UserPaid).unwrap();
+
+        testing_env!(setup_context(alice.clone())
+            .attached_deposit(NearToken::from_yoctonear(minimum + 500))
+            .prepaid_gas(Gas::from_tgas(300))
+            .build());
+        let balance = contract.storage_deposit(token.clone(), None, None);
+        testing_env_with_result(setup_context(current).build(), PromiseResult::Successful(vec![]));
+        contract.handle_storage_deposit(token.clone(), alice.clone(), U128(minimum), alice.clone(), balance);
+
+        // The minimum went to the token and stays there; only the kept part comes back
+        testing_env!(setup_context(alice.clone()).attached_deposit(NearToken::from_yoctonear(1)).build());
+        assert!(contract.storage_unregister(token.clone(), Some(true)));
+        assert_eq!(created_transfers(&alice), vec![500]);
+        assert_eq!(contract.get_registration_status(token, alice), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of exactly 1 yoctoNEAR")]
+    fn test_storage_withdraw_requires_one_yocto() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+
+        testing_env!(setup_context("alice.testnet".parse().unwrap()).build());
+        contract.storage_withdraw(token, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of exactly 1 yoctoNEAR")]
+    fn test_storage_unregister_requires_one_yocto() {
+        let manager: AccountId = "manager.testnet".parse().unwrap();
+        let token: AccountId = "token.testnet".parse().unwrap();
+        testing_env!(setup_context(manager.clone()).build());
+        let mut contract = new_contract(&manager);
+        contract.add_supported_token(token.clone()).unwrap();
+
+        testing_env!(setup_context("alice.testnet".parse().unwrap()).build());
+        contract.storage_unregister(token, Some(true));
+    }
+}
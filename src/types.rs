@@ -1,5 +1,5 @@
 use near_sdk::{AccountId, PublicKey, Gas, NearToken};
-use near_sdk::json_types::U128;
+use near_sdk::json_types::{U128, U64};
 use near_sdk::serde::{Serialize, Deserialize};
 use near_sdk::borsh::{self, BorshSerialize, BorshDeserialize};
 use near_sdk_macros::NearSchema;
@@ -42,6 +42,93 @@ pub enum Action {
     },
 }
 
+#[derive(NearSchema, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub enum ActionStatus {
+    Succeeded,
+    Failed,
+    /// Not run because an earlier action failed
+    Skipped,
+}
+
+/// Result of one action passed to `execute_actions`.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ActionOutcome {
+    pub index: u32,
+    pub status: ActionStatus,
+    pub error: Option<String>,
+}
+
+/// Actions of an `execute_actions` call still being run. Removed once every outcome is known.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ActionBatch {
+    pub id: u64,
+    pub sender: AccountId,
+    pub actions: Vec<Action>,
+    pub outcomes: Vec<ActionOutcome>,
+    /// Attached deposit not yet forwarded with a chain signature request
+    pub remaining_deposit: U128,
+    /// Block timestamp in milliseconds of the last action started
+    pub updated_at: u64,
+}
+
+/// ed25519 key an account registered to sign delegate actions.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct IntentKey {
+    pub public_key: PublicKey,
+    /// Block timestamp in milliseconds from which the key is no longer accepted
+    pub expires_at: Option<u64>,
+    /// Actions the key may sign (`ft_transfer`, `bridge_transfer`); empty allows all of them
+    pub methods: Vec<String>,
+}
+
+/// NEP-366 style delegate action whose `actions` are run by the wrapper as `sender_id`.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DelegateAction {
+    pub sender_id: AccountId,
+    pub receiver_id: AccountId,
+    pub actions: Vec<Action>,
+    pub nonce: u64,
+    pub max_block_height: u64,
+    pub public_key: PublicKey,
+}
+
+/// `signature` is the ed25519 signature of the NEP-461 prefixed hash of `delegate_action`.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SignedDelegateAction {
+    pub delegate_action: DelegateAction,
+    pub signature: Vec<u8>,
+}
+
+/// What the relayer is asked to carry out, passed to `relay_meta_transaction`.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub enum RelayOperation {
+    /// Deliver an outbound transfer; reported back with `complete_bridge_transfer(transfer_id)`
+    BridgeTransfer { transfer_id: u64 },
+}
+
+/// Value returned by the relayer's `relay_meta_transaction`.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RelayResponse {
+    pub accepted: bool,
+    pub reason: Option<String>,
+}
+
 #[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
 #[abi(borsh, json)]
 #[serde(crate = "near_sdk::serde")]
@@ -59,7 +146,186 @@ pub struct RequestChainSignatureArgs {
     pub token: AccountId,
     pub target_chain: String,
     pub derivation_path: String,
+    /// 32-byte hash to sign. Leave empty when `transaction` is given.
+    #[serde(default)]
     pub payload: Vec<u8>,
+    /// Transaction the contract encodes and hashes into the signing payload.
+    #[serde(default)]
+    pub transaction: Option<UnsignedTransaction>,
+}
+
+/// EIP-1559 transaction with an empty access list. `to` is a `0x`-prefixed address.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EvmTransaction {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub max_priority_fee_per_gas: U128,
+    pub max_fee_per_gas: U128,
+    pub gas_limit: u64,
+    pub to: String,
+    pub value: U128,
+    #[serde(default)]
+    pub data: Vec<u8>,
+}
+
+/// ERC-20 `transfer(recipient, amount)` call on the `token` contract.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Erc20Transfer {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub max_priority_fee_per_gas: U128,
+    pub max_fee_per_gas: U128,
+    pub gas_limit: u64,
+    pub token: String,
+    pub recipient: String,
+    pub amount: U128,
+}
+
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub enum UnsignedTransaction {
+    Evm(EvmTransaction),
+    Erc20Transfer(Erc20Transfer),
+    Bitcoin(BitcoinTransaction),
+}
+
+/// Script of a Bitcoin output spent by the MPC-derived key.
+#[derive(NearSchema, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub enum BitcoinScriptType {
+    /// Native segwit v0 pay-to-witness-pubkey-hash, as returned by `derived_address`
+    P2wpkh,
+}
+
+/// Output being spent. `txid` is hex in the usual display (reversed) byte order; `value` is in satoshis.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BitcoinInput {
+    pub txid: String,
+    pub vout: u32,
+    pub value: U64,
+    pub script_type: BitcoinScriptType,
+    /// Defaults to `0xffffffff`
+    pub sequence: Option<u32>,
+}
+
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BitcoinOutput {
+    pub address: String,
+    pub value: U64,
+}
+
+/// Bitcoin spend whose inputs are all controlled by the requester's derived key.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BitcoinTransaction {
+    pub version: u32,
+    pub lock_time: u32,
+    pub inputs: Vec<BitcoinInput>,
+    pub outputs: Vec<BitcoinOutput>,
+}
+
+/// Bitcoin transaction signed input by input. `signed_transaction` is set once every
+/// input's signature request is `Signed`.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BitcoinSpend {
+    pub id: u64,
+    pub requester: AccountId,
+    /// Legacy serialization without witnesses, as hashed for the txid
+    pub unsigned_transaction: Vec<u8>,
+    /// Compressed derived public key placed in every witness
+    pub public_key: Vec<u8>,
+    /// One signature request per input, in input order
+    pub signature_request_ids: Vec<u64>,
+    pub signed_transaction: Option<Vec<u8>>,
+}
+
+/// `request` argument of the MPC signer's `sign` method.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SignRequest {
+    pub payload: [u8; 32],
+    pub path: String,
+    pub key_version: u32,
+}
+
+/// Hex-encoded compressed `R` point of an MPC signature.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SignatureBigR {
+    pub affine_point: String,
+}
+
+/// Hex-encoded `s` scalar of an MPC signature.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SignatureScalar {
+    pub scalar: String,
+}
+
+/// Signature returned by the MPC signer's `sign` method.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SignatureResponse {
+    pub big_r: SignatureBigR,
+    pub s: SignatureScalar,
+    pub recovery_id: u8,
+}
+
+#[derive(NearSchema, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub enum SignatureRequestStatus {
+    /// The `sign` call is in flight
+    Pending,
+    /// The signer returned a signature, stored in `signature`
+    Signed,
+    /// The signer rejected or timed out the request; the requester can retry it
+    Failed,
+    /// No answer arrived within `signature_request_timeout_ms`; it can be retried once the
+    /// callback reports it failed
+    Expired,
+}
+
+/// Chain signature requested through the wrapper. `signature` is filled in by the signer callback.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SignatureRequest {
+    pub id: u64,
+    pub requester: AccountId,
+    pub target_chain: String,
+    pub derivation_path: String,
+    pub payload: Vec<u8>,
+    /// Encoded unsigned transaction when the payload was built from `transaction`
+    pub unsigned_transaction: Option<Vec<u8>>,
+    /// Bitcoin spend this request signs one input of
+    pub bitcoin_spend_id: Option<u64>,
+    pub status: SignatureRequestStatus,
+    pub signature: Option<SignatureResponse>,
+    /// Number of `sign` calls made; callbacks of superseded attempts are ignored
+    pub attempts: u32,
+    /// Signer fee attached to the latest `sign` call, paid back to the requester if it fails
+    pub deposit: U128,
+    /// Block timestamps in milliseconds
+    pub created_at: u64,
+    pub updated_at: u64,
 }
 
 #[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
@@ -72,6 +338,52 @@ pub struct BridgeTransferArgs {
     pub recipient: String,
 }
 
+/// JSON `msg` accepted by `ft_on_transfer` to bridge the received tokens out.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BridgeTransferMsg {
+    pub destination_chain: String,
+    pub recipient: String,
+}
+
+#[derive(NearSchema, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub enum BridgeTransferStatus {
+    /// Tokens are locked and the relay call is in flight
+    Pending,
+    /// The relayer accepted the transfer; waiting for it to report completion
+    Relayed,
+    /// The relayer reported delivery on the destination chain
+    Completed,
+    /// The relay or a refund attempt failed; the sender can request a refund
+    Failed,
+    /// A refund of the locked tokens is in flight
+    Refunding,
+    /// The locked tokens were returned to the sender
+    Refunded,
+    /// The relayer reported delivery and the bridged tokens are being burned
+    Completing,
+}
+
+/// Outbound bridge transfer backed by tokens locked in the wrapper.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BridgeTransfer {
+    pub id: u64,
+    pub sender: AccountId,
+    pub token: AccountId,
+    pub amount: U128,
+    pub destination_chain: String,
+    pub recipient: String,
+    pub status: BridgeTransferStatus,
+    /// Block timestamps in milliseconds
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
 #[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
 #[abi(borsh, json)]
 #[serde(crate = "near_sdk::serde")]
@@ -85,6 +397,96 @@ pub struct FinalizeTransferArgs {
     pub message_payload: Vec<u8>,
 }
 
+/// Current wire format version of [`BridgeMessage`].
+pub const BRIDGE_MESSAGE_VERSION: u8 = 1;
+
+/// Canonical inbound bridge message. Relayers borsh-encode it into
+/// `FinalizeTransferArgs.message_payload` and the MPC network signs the encoded bytes.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BridgeMessage {
+    pub version: u8,
+    pub source_chain: String,
+    pub nonce: u64,
+    pub token: AccountId,
+    pub recipient: AccountId,
+    pub amount: U128,
+    pub is_native: bool,
+    /// Block timestamp in milliseconds after which the message is rejected
+    pub expires_at: u64,
+}
+
+/// Address family of a registered chain, used to validate recipients.
+#[derive(NearSchema, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub enum AddressFormat {
+    Evm,
+    Bitcoin,
+    BitcoinTestnet,
+    Solana,
+}
+
+/// Manager-controlled configuration of a chain the bridge can send to and receive from.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ChainConfig {
+    pub chain_id: String,
+    pub display_name: String,
+    pub address_format: AddressFormat,
+    /// Bounds on the amount of a single outbound transfer
+    pub min_amount: U128,
+    pub max_amount: U128,
+    pub enabled: bool,
+    pub required_confirmations: u32,
+}
+
+/// Foreign asset represented on NEAR by a wrapped token deployed by the factory.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BridgedAsset {
+    pub source_chain: String,
+    pub address: String,
+}
+
+/// NEP-148 metadata passed to the `new` method of a freshly deployed bridged token.
+#[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BridgedTokenMetadata {
+    pub spec: String,
+    pub name: String,
+    pub symbol: String,
+    pub icon: Option<String>,
+    pub decimals: u8,
+}
+
+/// Who pays the token's storage deposit when an account is registered without one attached.
+#[derive(NearSchema, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub enum StorageSponsorship {
+    /// The wrapper pays from its own balance
+    Sponsored,
+    /// Callers must attach the deposit; transfers to unregistered accounts are rejected
+    UserPaid,
+}
+
+/// Where the wrapper's storage deposit for an account on a token stands.
+#[derive(NearSchema, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, BorshSerialize, BorshDeserialize)]
+#[abi(borsh, json)]
+#[serde(crate = "near_sdk::serde")]
+pub enum RegistrationStatus {
+    /// Deposit sent, the token has not answered yet
+    Pending,
+    Registered,
+    /// The token rejected the deposit; the next registration attempt pays it again
+    Failed,
+}
+
 #[derive(NearSchema, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
 #[abi(borsh, json)]
 #[serde(crate = "near_sdk::serde")]